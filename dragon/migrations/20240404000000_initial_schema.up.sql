@@ -38,7 +38,7 @@ CREATE TABLE IF NOT EXISTS credentials (
 );
 
 -- Create indexes
-CREATE INDEX idx_categories_user_id ON categories(user_id);
-CREATE INDEX idx_categories_parent_id ON categories(parent_id);
-CREATE INDEX idx_credentials_user_id ON credentials(user_id);
-CREATE INDEX idx_credentials_category_id ON credentials(category_id); 
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS idx_categories_user_id ON categories(user_id);
+CREATE INDEX IF NOT EXISTS idx_categories_parent_id ON categories(parent_id);
+CREATE INDEX IF NOT EXISTS idx_credentials_user_id ON credentials(user_id);
+CREATE INDEX IF NOT EXISTS idx_credentials_category_id ON credentials(category_id); 
\ No newline at end of file
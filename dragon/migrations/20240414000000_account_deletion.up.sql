@@ -0,0 +1,13 @@
+-- Pending account deletions
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_requested_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_scheduled_for TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_deletion_scheduled_for ON users(deletion_scheduled_for) WHERE deletion_scheduled_for IS NOT NULL;
+
+-- Minimal record that an account existed and was deleted (no personal data)
+CREATE TABLE IF NOT EXISTS account_deletions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL, -- No foreign key: the user row is gone
+    requested_at TIMESTAMPTZ,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,38 @@
+-- The initial schema had no username column. Existing accounts get the local part
+-- of their email, oldest account first, with "-2", "-3", ... added when it is taken.
+-- Usernames cannot contain "@", so a login identifier never matches both the
+-- username of one account and the email of another.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS username VARCHAR(255);
+
+DO $$
+DECLARE
+    account RECORD;
+    base TEXT;
+    candidate TEXT;
+    suffix INTEGER;
+BEGIN
+    FOR account IN SELECT id, email FROM users WHERE username IS NULL ORDER BY created_at, id LOOP
+        base := left(regexp_replace(split_part(account.email, '@', 1), '[@[:space:][:cntrl:]]', '', 'g'), 56);
+        IF base = '' THEN
+            base := 'user';
+        END IF;
+
+        candidate := base;
+        suffix := 1;
+        WHILE EXISTS (SELECT 1 FROM users WHERE lower(username) = lower(candidate)) LOOP
+            suffix := suffix + 1;
+            candidate := base || '-' || suffix;
+        END LOOP;
+
+        UPDATE users SET username = candidate WHERE id = account.id;
+    END LOOP;
+END $$;
+
+ALTER TABLE users ALTER COLUMN username SET NOT NULL;
+
+-- Usernames and emails are unique regardless of case.
+-- The index names match the old constraints so error mapping keeps working.
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_username_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_key ON users (lower(email));
+CREATE UNIQUE INDEX IF NOT EXISTS users_username_key ON users (lower(username));
@@ -0,0 +1,2 @@
+-- New email address awaiting confirmation
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email VARCHAR(255);
@@ -0,0 +1,15 @@
+-- Every sign-in attempt, successful or not
+CREATE TABLE IF NOT EXISTS login_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE, -- NULL when the identifier matched no account
+    identifier VARCHAR(255) NOT NULL,
+    success BOOLEAN NOT NULL,
+    ip TEXT,
+    ip_range TEXT, -- /24 or /48 network, used to spot new locations
+    user_agent TEXT,
+    factor VARCHAR(50), -- e.g. "password" or "password+totp"
+    failure_reason TEXT, -- a fixed code such as "invalid_password", never error text
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_events_user_id_created_at ON login_events(user_id, created_at DESC);
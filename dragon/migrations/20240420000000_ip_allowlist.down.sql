@@ -0,0 +1 @@
+DROP TABLE IF EXISTS ip_allowlist_entries;
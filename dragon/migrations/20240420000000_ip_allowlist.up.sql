@@ -0,0 +1,13 @@
+-- CIDR ranges an account may be used from
+CREATE TABLE IF NOT EXISTS ip_allowlist_entries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    cidr VARCHAR(64) NOT NULL,
+    description VARCHAR(255),
+    managed_by VARCHAR(10) NOT NULL DEFAULT 'user' CHECK (managed_by IN ('user', 'admin')), -- Admin entries are policy the user cannot change
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, managed_by, cidr)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ip_allowlist_entries_user_id ON ip_allowlist_entries(user_id);
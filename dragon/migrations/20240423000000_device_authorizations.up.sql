@@ -0,0 +1,19 @@
+-- OAuth 2.0 device authorization grant (RFC 8628)
+CREATE TABLE IF NOT EXISTS device_authorizations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    device_code_hash VARCHAR(64) NOT NULL UNIQUE, -- SHA-256 of the device code
+    user_code_hash VARCHAR(64) NOT NULL UNIQUE,   -- SHA-256 of the normalized user code
+    client_id VARCHAR(255) NOT NULL,
+    device_name VARCHAR(255),
+    ip TEXT,         -- Address that requested the code
+    user_agent TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'denied', 'issued')),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    interval_secs INTEGER NOT NULL,
+    last_polled_at TIMESTAMPTZ,
+    decided_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_authorizations_user_id ON device_authorizations(user_id);
@@ -0,0 +1,10 @@
+ALTER TABLE credentials DROP CONSTRAINT IF EXISTS credentials_category_id_fkey;
+ALTER TABLE credentials ADD CONSTRAINT credentials_category_id_fkey
+    FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE SET NULL;
+ALTER TABLE credentials ALTER COLUMN category_id DROP NOT NULL;
+ALTER TABLE credentials ALTER COLUMN username DROP NOT NULL;
+ALTER TABLE credentials ALTER COLUMN username DROP DEFAULT;
+ALTER TABLE credentials RENAME COLUMN password TO password_encrypted;
+ALTER TABLE categories DROP COLUMN IF EXISTS description;
+ALTER TABLE users ALTER COLUMN totp_enabled DROP NOT NULL;
+ALTER TABLE users DROP COLUMN IF EXISTS last_login;
@@ -0,0 +1,48 @@
+-- Columns the models read that the earlier migrations never created
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_login TIMESTAMPTZ;
+UPDATE users SET totp_enabled = FALSE WHERE totp_enabled IS NULL;
+ALTER TABLE users ALTER COLUMN totp_enabled SET NOT NULL;
+
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS description TEXT;
+
+-- The handlers store the encrypted password in credentials.password
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'credentials' AND column_name = 'password_encrypted'
+    ) THEN
+        ALTER TABLE credentials RENAME COLUMN password_encrypted TO password;
+    END IF;
+END
+$$;
+
+UPDATE credentials SET username = '' WHERE username IS NULL;
+ALTER TABLE credentials ALTER COLUMN username SET DEFAULT '';
+ALTER TABLE credentials ALTER COLUMN username SET NOT NULL;
+
+-- Every credential lives in a category; uncategorised ones move to a top-level "Unfiled" per user
+INSERT INTO categories (user_id, name)
+SELECT DISTINCT c.user_id, 'Unfiled'
+FROM credentials c
+WHERE c.category_id IS NULL
+  AND NOT EXISTS (
+      SELECT 1 FROM categories cat
+      WHERE cat.user_id = c.user_id AND cat.name = 'Unfiled' AND cat.parent_id IS NULL
+  );
+
+UPDATE credentials c
+SET category_id = (
+    SELECT cat.id FROM categories cat
+    WHERE cat.user_id = c.user_id AND cat.name = 'Unfiled' AND cat.parent_id IS NULL
+    ORDER BY cat.created_at
+    LIMIT 1
+)
+WHERE c.category_id IS NULL;
+
+ALTER TABLE credentials ALTER COLUMN category_id SET NOT NULL;
+
+-- SET NULL is no longer possible, so a category with credentials in it cannot be deleted
+ALTER TABLE credentials DROP CONSTRAINT IF EXISTS credentials_category_id_fkey;
+ALTER TABLE credentials ADD CONSTRAINT credentials_category_id_fkey
+    FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE RESTRICT;
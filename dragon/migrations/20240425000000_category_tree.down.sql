@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_categories_user_id_root_name;
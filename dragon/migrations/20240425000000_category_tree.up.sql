@@ -0,0 +1,12 @@
+-- The (user_id, name, parent_id) constraint treats NULL parents as distinct, so
+-- top-level names need their own index. Existing duplicates get a numeric suffix.
+UPDATE categories c
+SET name = c.name || ' (' || d.n || ')'
+FROM (
+    SELECT id, row_number() OVER (PARTITION BY user_id, name ORDER BY created_at, id) AS n
+    FROM categories
+    WHERE parent_id IS NULL
+) d
+WHERE c.id = d.id AND d.n > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_user_id_root_name ON categories(user_id, name) WHERE parent_id IS NULL;
@@ -0,0 +1,11 @@
+-- Categories and credentials removed by a recursive delete, kept as full row snapshots
+CREATE TABLE IF NOT EXISTS trash_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_type VARCHAR(16) NOT NULL CHECK (item_type IN ('category', 'credential')),
+    item_id UUID NOT NULL,
+    data JSONB NOT NULL,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_trash_items_user_id ON trash_items(user_id, deleted_at DESC);
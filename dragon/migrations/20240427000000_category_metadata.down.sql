@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_categories_parent_id_position;
+ALTER TABLE categories DROP COLUMN IF EXISTS collapsed;
+ALTER TABLE categories DROP COLUMN IF EXISTS icon;
+ALTER TABLE categories DROP COLUMN IF EXISTS color;
+ALTER TABLE categories DROP COLUMN IF EXISTS position;
@@ -0,0 +1,16 @@
+-- Manual ordering and display settings for categories
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS color VARCHAR(7);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS icon VARCHAR(64);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS collapsed BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Keep the current alphabetical order as the starting position
+UPDATE categories c
+SET position = o.position
+FROM (
+    SELECT id, row_number() OVER (PARTITION BY user_id, parent_id ORDER BY name) - 1 AS position
+    FROM categories
+) o
+WHERE c.id = o.id;
+
+CREATE INDEX IF NOT EXISTS idx_categories_parent_id_position ON categories(user_id, parent_id, position);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_credentials_user_id_item_type;
+ALTER TABLE credentials DROP COLUMN IF EXISTS metadata;
+ALTER TABLE credentials DROP COLUMN IF EXISTS payload;
+ALTER TABLE credentials DROP COLUMN IF EXISTS item_type;
@@ -0,0 +1,7 @@
+-- Vault items other than logins keep their typed fields as encrypted JSON
+ALTER TABLE credentials ADD COLUMN IF NOT EXISTS item_type VARCHAR(32) NOT NULL DEFAULT 'login'
+    CHECK (item_type IN ('login', 'secure_note', 'card', 'identity', 'api_key', 'ssh_key', 'database'));
+ALTER TABLE credentials ADD COLUMN IF NOT EXISTS payload TEXT;
+ALTER TABLE credentials ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}'; -- Non-secret summary for lists
+
+CREATE INDEX IF NOT EXISTS idx_credentials_user_id_item_type ON credentials(user_id, item_type);
@@ -0,0 +1,14 @@
+-- Ordered extra values on a credential. Values are stored as text: 'true'/'false'
+-- for booleans, YYYY-MM-DD for dates and encrypted like passwords for hidden fields.
+CREATE TABLE IF NOT EXISTS credential_fields (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    credential_id UUID NOT NULL REFERENCES credentials(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    label VARCHAR(100) NOT NULL,
+    field_type VARCHAR(16) NOT NULL CHECK (field_type IN ('text', 'hidden', 'boolean', 'url', 'date')),
+    value TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_credential_fields_credential_id ON credential_fields(credential_id, position);
@@ -0,0 +1,29 @@
+-- Snapshots of categories and credentials after each change. Secret columns stay
+-- encrypted as they are in the main tables, except login passwords, which are only
+-- kept in password_history. Rows outlive the items they describe.
+CREATE TABLE IF NOT EXISTS revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_type VARCHAR(16) NOT NULL CHECK (item_type IN ('category', 'credential')),
+    item_id UUID NOT NULL,
+    version INTEGER NOT NULL,
+    changed_by UUID NOT NULL,
+    changed_fields TEXT[] NOT NULL DEFAULT '{}',
+    data JSONB NOT NULL CHECK (NOT data ? 'password'),
+    rollback_of UUID REFERENCES revisions(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (item_type, item_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_revisions_user_id_item ON revisions(user_id, item_type, item_id, version DESC);
+
+-- Revisions are never edited; they only go when their owner's account does
+CREATE OR REPLACE FUNCTION revisions_immutable() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'revisions are immutable';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS revisions_immutable ON revisions;
+CREATE TRIGGER revisions_immutable BEFORE UPDATE ON revisions
+    FOR EACH ROW EXECUTE FUNCTION revisions_immutable();
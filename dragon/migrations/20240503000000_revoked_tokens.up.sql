@@ -0,0 +1,9 @@
+-- Tokens ended by logging out, kept until they would have expired anyway
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    token_id UUID PRIMARY KEY, -- The token's jti claim
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::env;
+
+use crate::{
+    auth::{
+        identity::normalize_identifier,
+        ldap::{Ldap3Directory, LdapBackend, LdapConfig},
+        password::verify_password,
+    },
+    errors::AppError,
+    models::user::User,
+};
+
+// The same error for an unknown account and a wrong password, so sign-in does not
+// reveal which accounts exist
+pub fn invalid_credentials() -> AppError {
+    AppError::Unauthorized("Invalid username or password".to_string())
+}
+
+// Checks a login identifier (username or email) and password and resolves the local user
+#[async_trait]
+pub trait AuthBackend: Send + Sync {
+    async fn authenticate(&self, pool: &PgPool, identifier: &str, password: &str) -> Result<User, AppError>;
+}
+
+// Argon2 password hashes stored in the users table
+pub struct LocalBackend;
+
+#[async_trait]
+impl AuthBackend for LocalBackend {
+    async fn authenticate(&self, pool: &PgPool, identifier: &str, password: &str) -> Result<User, AppError> {
+        // Either the username or the email, compared case-insensitively.
+        // Directory-managed accounts have no usable local password.
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            SELECT * FROM users
+            WHERE (lower(username) = lower($1) OR lower(email) = lower($1))
+              AND auth_source = 'local'
+            "#,
+        )
+        .bind(normalize_identifier(identifier))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(invalid_credentials)?;
+
+        if !verify_password(password, &user.password_hash)? {
+            return Err(invalid_credentials());
+        }
+
+        Ok(user)
+    }
+}
+
+// Build the backend selected by AUTH_BACKEND ("local" by default, or "ldap")
+pub fn backend_from_env() -> Result<Box<dyn AuthBackend>, AppError> {
+    let backend = env::var("AUTH_BACKEND").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" => Ok(Box::new(LocalBackend)),
+        "ldap" => Ok(Box::new(LdapBackend::new(LdapConfig::from_env()?, Ldap3Directory))),
+        other => Err(AppError::Internal(format!("Unknown AUTH_BACKEND: {}", other))),
+    }
+}
@@ -0,0 +1,123 @@
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::{PgExecutor, PgPool};
+use std::env;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    auth::tokens::{generate_token, hash_token},
+    errors::AppError,
+    middleware::client::ClientInfo,
+    models::user::TrustedDevice,
+};
+
+// Days a trusted device may skip TOTP when TRUSTED_DEVICE_DAYS is not set
+const DEFAULT_TRUSTED_DEVICE_DAYS: i64 = 30;
+
+// Longest device name we keep
+const MAX_DEVICE_NAME_LENGTH: usize = 255;
+
+// How long a newly trusted device skips TOTP
+pub fn trusted_device_lifetime() -> Duration {
+    let days = env::var("TRUSTED_DEVICE_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TRUSTED_DEVICE_DAYS);
+    Duration::days(days)
+}
+
+// MAC binding a device secret to its user, keyed by the server secret
+fn device_mac(user_id: Uuid, secret: &str) -> Result<Hmac<Sha256>, AppError> {
+    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_secret.as_bytes())
+        .map_err(|e| AppError::Internal(format!("HMAC error: {}", e)))?;
+    mac.update(format!("trusted-device:{}:{}", user_id, secret).as_bytes());
+    Ok(mac)
+}
+
+fn sign(user_id: Uuid, secret: &str) -> Result<String, AppError> {
+    Ok(BASE64URL_NOPAD.encode(&device_mac(user_id, secret)?.finalize().into_bytes()))
+}
+
+// Whether a device token carries a valid signature for the user
+fn verify_signature(user_id: Uuid, token: &str) -> Result<bool, AppError> {
+    let Some((secret, signature)) = token.split_once('.') else {
+        return Ok(false);
+    };
+    let Ok(signature) = BASE64URL_NOPAD.decode(signature.as_bytes()) else {
+        return Ok(false);
+    };
+
+    Ok(device_mac(user_id, secret)?.verify_slice(&signature).is_ok())
+}
+
+// Trust the device a login came from and return its token. Only the hash is stored.
+pub async fn trust_device(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: Option<&str>,
+    client: &ClientInfo,
+) -> Result<(String, TrustedDevice), AppError> {
+    let secret = generate_token();
+    let token = format!("{}.{}", secret, sign(user_id, &secret)?);
+    let expires_at = OffsetDateTime::now_utc() + trusted_device_lifetime();
+
+    // Fall back to the user agent so the device list means something
+    let name: Option<String> = name
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .or(client.user_agent.as_deref())
+        .map(|name| name.chars().take(MAX_DEVICE_NAME_LENGTH).collect());
+
+    let device = sqlx::query_as::<_, TrustedDevice>(
+        r#"
+        INSERT INTO trusted_devices (user_id, token_hash, name, ip, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, name, ip, created_at, last_used_at, expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(name)
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((token, device))
+}
+
+// Check a device token for a user, recording its use when it is valid
+pub async fn use_trusted_device(pool: &PgPool, user_id: Uuid, token: &str, client: &ClientInfo) -> Result<bool, AppError> {
+    // Forged or mistyped tokens never reach the database
+    if !verify_signature(user_id, token)? {
+        return Ok(false);
+    }
+
+    let used = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE trusted_devices SET last_used_at = now(), ip = $3
+        WHERE user_id = $1 AND token_hash = $2 AND expires_at > now()
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(token))
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(used.is_some())
+}
+
+// Forget every trusted device, e.g. after the password changes
+pub async fn revoke_trusted_devices<'e, E: PgExecutor<'e>>(executor: E, user_id: Uuid) -> Result<u64, AppError> {
+    let result = sqlx::query("DELETE FROM trusted_devices WHERE user_id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,132 @@
+use sqlx::{FromRow, PgPool};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    auth::{tokens::hash_token, totp::generate_numeric_code},
+    errors::AppError,
+    mail::{send_best_effort, Email},
+    models::user::User,
+};
+
+// Shape and lifetime of emailed codes
+const EMAIL_CODE_DIGITS: usize = 6;
+const EMAIL_CODE_LIFETIME: Duration = Duration::minutes(10);
+
+// Wrong guesses before a code is burned
+const MAX_EMAIL_CODE_ATTEMPTS: i32 = 5;
+
+// Minimum gap between two emailed codes
+const EMAIL_CODE_RESEND_INTERVAL: Duration = Duration::seconds(60);
+
+// Six digits are cheap to brute force, so the hash is salted with the user
+fn hash_code(user_id: Uuid, code: &str) -> String {
+    hash_token(&format!("{}:{}", user_id, code))
+}
+
+#[derive(FromRow)]
+struct ActiveCode {
+    id: Uuid,
+    code_hash: String,
+    attempts: i32,
+    created_at: OffsetDateTime,
+}
+
+// Email a fresh code, replacing any outstanding one. Repeated requests within
+// the resend interval keep the code already sent.
+pub async fn send_email_code(pool: &PgPool, user: &User) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let latest = sqlx::query_as::<_, ActiveCode>(
+        r#"
+        SELECT id, code_hash, attempts, created_at FROM email_otp_codes
+        WHERE user_id = $1 AND used_at IS NULL AND expires_at > now()
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if latest.is_some_and(|code| OffsetDateTime::now_utc() - code.created_at < EMAIL_CODE_RESEND_INTERVAL) {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE email_otp_codes SET used_at = now() WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let code = generate_numeric_code(EMAIL_CODE_DIGITS);
+    sqlx::query(
+        r#"
+        INSERT INTO email_otp_codes (user_id, code_hash, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user.id)
+    .bind(hash_code(user.id, &code))
+    .bind(OffsetDateTime::now_utc() + EMAIL_CODE_LIFETIME)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "Your DragonFruit sign-in code".to_string(),
+        body: format!(
+            "Hi {},\n\nYour sign-in code is {}. It expires in {} minutes and can be used once.\n\n\
+             If you did not try to sign in, change your password.\n",
+            user.username,
+            code,
+            EMAIL_CODE_LIFETIME.whole_minutes()
+        ),
+    })
+    .await;
+
+    Ok(())
+}
+
+// Check a code against the user's outstanding one. A match uses it up; too many
+// misses burn it so a new one has to be requested.
+pub async fn verify_email_code(pool: &PgPool, user_id: Uuid, code: &str) -> Result<bool, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let Some(active) = sqlx::query_as::<_, ActiveCode>(
+        r#"
+        SELECT id, code_hash, attempts, created_at FROM email_otp_codes
+        WHERE user_id = $1 AND used_at IS NULL AND expires_at > now()
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let matches = active.code_hash == hash_code(user_id, code.trim());
+    let attempts = active.attempts + 1;
+
+    sqlx::query(
+        r#"
+        UPDATE email_otp_codes
+        SET attempts = $2, used_at = CASE WHEN $3 THEN now() ELSE NULL END
+        WHERE id = $1
+        "#,
+    )
+    .bind(active.id)
+    .bind(attempts)
+    .bind(matches || attempts >= MAX_EMAIL_CODE_ATTEMPTS)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(matches)
+}
@@ -0,0 +1,53 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::errors::AppError;
+
+// Longest username we accept
+const MAX_USERNAME_LENGTH: usize = 64;
+
+// Canonical form of a username: NFKC, trimmed, no whitespace or control characters.
+// Uniqueness is case-insensitive in the database, so case is kept for display.
+pub fn normalize_username(username: &str) -> Result<String, AppError> {
+    let normalized: String = username.nfkc().collect::<String>().trim().to_string();
+
+    if normalized.is_empty() {
+        return Err(AppError::BadRequest("Username cannot be empty".to_string()));
+    }
+
+    if normalized.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(AppError::BadRequest(format!(
+            "Username must be at most {} characters long", MAX_USERNAME_LENGTH
+        )));
+    }
+
+    if normalized.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(AppError::BadRequest("Username cannot contain spaces".to_string()));
+    }
+
+    if normalized.contains('@') {
+        return Err(AppError::BadRequest("Username cannot contain '@'".to_string()));
+    }
+
+    Ok(normalized)
+}
+
+// Canonical form of an email address: NFKC, trimmed, with a lowercase domain
+pub fn normalize_email(email: &str) -> Result<String, AppError> {
+    let normalized: String = email.nfkc().collect::<String>().trim().to_string();
+
+    let (local, domain) = normalized
+        .rsplit_once('@')
+        .filter(|(local, domain)| !local.is_empty() && domain.contains('.'))
+        .ok_or_else(|| AppError::BadRequest("Invalid email address".to_string()))?;
+
+    if normalized.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(AppError::BadRequest("Invalid email address".to_string()));
+    }
+
+    Ok(format!("{}@{}", local, domain.to_lowercase()))
+}
+
+// Normalize a login identifier the same way as what it might match
+pub fn normalize_identifier(identifier: &str) -> String {
+    identifier.nfkc().collect::<String>().trim().to_string()
+}
@@ -31,10 +31,23 @@ pub struct Claims {
     pub iss: String,     // Issuer
     pub iat: i64,        // Issued at (timestamp)
     pub exp: i64,        // Expiration time (timestamp)
+    #[serde(default)]
+    pub ver: i32,        // User's token version when issued
+    #[serde(default)]
+    pub jti: Option<Uuid>, // Token ID, used to revoke this one token on logout
+}
+
+// Identity carried by a validated token
+#[derive(Debug, Clone, Copy)]
+pub struct TokenSubject {
+    pub user_id: Uuid,
+    pub token_version: i32,
+    pub token_id: Option<Uuid>, // Tokens issued before IDs were added have none
+    pub expires_at: i64,
 }
 
 // Create a JWT token for a user
-pub fn create_token(user_id: Uuid, config: &TokenConfig) -> Result<String, AppError> {
+pub fn create_token(user_id: Uuid, token_version: i32, config: &TokenConfig) -> Result<String, AppError> {
     let now = OffsetDateTime::now_utc().unix_timestamp();
     let expires_at = now + config.expires_in;
     
@@ -43,6 +56,8 @@ pub fn create_token(user_id: Uuid, config: &TokenConfig) -> Result<String, AppEr
         iss: config.issuer.clone(),
         iat: now,
         exp: expires_at,
+        ver: token_version,
+        jti: Some(Uuid::new_v4()),
     };
     
     let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
@@ -57,7 +72,7 @@ pub fn create_token(user_id: Uuid, config: &TokenConfig) -> Result<String, AppEr
 }
 
 // Validate a JWT token
-pub fn validate_token(token: &str) -> Result<Uuid, AppError> {
+pub fn validate_token(token: &str) -> Result<TokenSubject, AppError> {
     let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     let key = DecodingKey::from_secret(jwt_secret.as_bytes());
     let validation = Validation::new(Algorithm::HS256);
@@ -74,5 +89,10 @@ pub fn validate_token(token: &str) -> Result<Uuid, AppError> {
     let user_id = Uuid::parse_str(&token_data.claims.sub)
         .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))?;
     
-    Ok(user_id)
+    Ok(TokenSubject {
+        user_id,
+        token_version: token_data.claims.ver,
+        token_id: token_data.claims.jti,
+        expires_at: token_data.claims.exp,
+    })
 } 
\ No newline at end of file
@@ -0,0 +1,344 @@
+use async_trait::async_trait;
+use ldap3::{ldap_escape, LdapConnAsync, Scope, SearchEntry};
+use rand::Rng;
+use sqlx::PgPool;
+use std::env;
+
+use crate::{
+    auth::{
+        backend::{invalid_credentials, AuthBackend},
+        identity::{normalize_email, normalize_username},
+        password::hash_password,
+    },
+    errors::AppError,
+    models::user::User,
+};
+
+// LDAP result code for a failed simple bind
+const INVALID_CREDENTIALS: u32 = 49;
+
+// Connection and lookup settings for the directory
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    pub url: String,
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    pub base_dn: String,
+    pub user_filter: String, // "{username}" is replaced with the escaped login name
+    pub email_attribute: String,
+}
+
+impl LdapConfig {
+    // Read the directory settings from LDAP_* environment variables
+    pub fn from_env() -> Result<Self, AppError> {
+        let required = |name: &str| {
+            env::var(name).map_err(|_| AppError::Internal(format!("{} must be set", name)))
+        };
+
+        Ok(Self {
+            url: required("LDAP_URL")?,
+            bind_dn: env::var("LDAP_BIND_DN").ok(),
+            bind_password: env::var("LDAP_BIND_PASSWORD").ok(),
+            base_dn: required("LDAP_BASE_DN")?,
+            user_filter: env::var("LDAP_USER_FILTER")
+                .unwrap_or_else(|_| "(uid={username})".to_string()),
+            email_attribute: env::var("LDAP_EMAIL_ATTRIBUTE")
+                .unwrap_or_else(|_| "mail".to_string()),
+        })
+    }
+}
+
+// A user entry found in the directory
+#[derive(Debug, Clone)]
+pub struct DirectoryEntry {
+    pub dn: String,
+    pub email: Option<String>,
+}
+
+// Operations the LDAP backend needs from a directory server
+#[async_trait]
+pub trait Directory: Send + Sync {
+    // Search for the entries matching a login name
+    async fn find_users(&self, config: &LdapConfig, username: &str) -> Result<Vec<DirectoryEntry>, AppError>;
+
+    // Simple-bind as an entry to check its password
+    async fn bind(&self, config: &LdapConfig, dn: &str, password: &str) -> Result<bool, AppError>;
+}
+
+// Directory backed by a real LDAP server through ldap3
+pub struct Ldap3Directory;
+
+fn ldap_error(e: ldap3::LdapError) -> AppError {
+    AppError::Internal(format!("LDAP error: {}", e))
+}
+
+#[async_trait]
+impl Directory for Ldap3Directory {
+    async fn find_users(&self, config: &LdapConfig, username: &str) -> Result<Vec<DirectoryEntry>, AppError> {
+        let (conn, mut ldap) = LdapConnAsync::new(&config.url).await.map_err(ldap_error)?;
+        ldap3::drive!(conn);
+
+        // Use the service account if one is configured, otherwise search anonymously
+        if let (Some(bind_dn), Some(bind_password)) = (&config.bind_dn, &config.bind_password) {
+            ldap.simple_bind(bind_dn, bind_password)
+                .await
+                .and_then(|r| r.success())
+                .map_err(ldap_error)?;
+        }
+
+        let filter = config.user_filter.replace("{username}", &ldap_escape(username));
+        let (entries, _) = ldap
+            .search(&config.base_dn, Scope::Subtree, &filter, vec![config.email_attribute.as_str()])
+            .await
+            .and_then(|r| r.success())
+            .map_err(ldap_error)?;
+
+        ldap.unbind().await.map_err(ldap_error)?;
+
+        Ok(entries.into_iter().map(|entry| {
+            let entry = SearchEntry::construct(entry);
+            let email = entry.attrs
+                .get(&config.email_attribute)
+                .and_then(|values| values.first().cloned());
+
+            DirectoryEntry { dn: entry.dn, email }
+        }).collect())
+    }
+
+    async fn bind(&self, config: &LdapConfig, dn: &str, password: &str) -> Result<bool, AppError> {
+        let (conn, mut ldap) = LdapConnAsync::new(&config.url).await.map_err(ldap_error)?;
+        ldap3::drive!(conn);
+
+        let result = ldap.simple_bind(dn, password).await.map_err(ldap_error)?;
+        let _ = ldap.unbind().await;
+
+        match result.rc {
+            0 => Ok(true),
+            INVALID_CREDENTIALS => Ok(false),
+            _ => result.success().map(|_| true).map_err(ldap_error),
+        }
+    }
+}
+
+// Authenticates against a directory and maps entries to local users
+pub struct LdapBackend<D: Directory> {
+    config: LdapConfig,
+    directory: D,
+}
+
+impl<D: Directory> LdapBackend<D> {
+    pub fn new(config: LdapConfig, directory: D) -> Self {
+        Self { config, directory }
+    }
+
+    // Find the local row for a directory entry, creating it on first sign-in
+    async fn local_user(&self, pool: &PgPool, username: &str, entry: &DirectoryEntry) -> Result<User, AppError> {
+        let existing = sqlx::query_as::<_, User>(
+            "SELECT * FROM users WHERE auth_source = 'ldap' AND external_id = $1",
+        )
+        .bind(&entry.dn)
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(user) = existing {
+            return Ok(user);
+        }
+
+        let email = entry.email.as_deref()
+            .ok_or_else(|| AppError::BadRequest("Directory entry has no email address".to_string()))?;
+        let email = normalize_email(email)?;
+        let username = normalize_username(username)?;
+
+        // Directory users never sign in locally, so store a hash of random bytes
+        let random_password: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let password_hash = hash_password(&random_password)?;
+
+        sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (username, email, password_hash, auth_source, external_id)
+            VALUES ($1, $2, $3, 'ldap', $4)
+            RETURNING *
+            "#,
+        )
+        .bind(&username)
+        .bind(&email)
+        .bind(&password_hash)
+        .bind(&entry.dn)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            if e.to_string().contains("duplicate key") {
+                AppError::Conflict("A local account with this username or email already exists".to_string())
+            } else {
+                AppError::Database(e)
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl<D: Directory> AuthBackend for LdapBackend<D> {
+    async fn authenticate(&self, pool: &PgPool, identifier: &str, password: &str) -> Result<User, AppError> {
+        // An empty password would be an unauthenticated bind, which most servers accept
+        if password.is_empty() {
+            return Err(invalid_credentials());
+        }
+
+        let mut entries = self.directory.find_users(&self.config, identifier).await?;
+
+        // An ambiguous filter must not pick an arbitrary account
+        if entries.len() > 1 {
+            return Err(AppError::Internal(format!("LDAP filter matched {} entries", entries.len())));
+        }
+
+        let entry = entries.pop().ok_or_else(invalid_credentials)?;
+
+        if !self.directory.bind(&self.config, &entry.dn, password).await? {
+            return Err(invalid_credentials());
+        }
+
+        self.local_user(pool, identifier, &entry).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    // In-process stand-in for a directory: login name, entry and password
+    struct FakeDirectory {
+        users: Vec<(String, DirectoryEntry, String)>,
+        binds: AtomicUsize,
+    }
+
+    impl FakeDirectory {
+        fn new(users: &[(&str, &str, Option<&str>, &str)]) -> Self {
+            let users = users
+                .iter()
+                .map(|(login, dn, email, password)| {
+                    let entry = DirectoryEntry { dn: dn.to_string(), email: email.map(str::to_string) };
+                    (login.to_string(), entry, password.to_string())
+                })
+                .collect();
+            Self { users, binds: AtomicUsize::new(0) }
+        }
+    }
+
+    #[async_trait]
+    impl Directory for FakeDirectory {
+        async fn find_users(&self, _config: &LdapConfig, username: &str) -> Result<Vec<DirectoryEntry>, AppError> {
+            Ok(self.users.iter().filter(|(login, _, _)| login == username).map(|(_, entry, _)| entry.clone()).collect())
+        }
+
+        async fn bind(&self, _config: &LdapConfig, dn: &str, password: &str) -> Result<bool, AppError> {
+            self.binds.fetch_add(1, Ordering::SeqCst);
+            Ok(self.users.iter().any(|(_, entry, secret)| entry.dn == dn && secret == password))
+        }
+    }
+
+    fn config() -> LdapConfig {
+        LdapConfig {
+            url: "ldap://directory.test".to_string(),
+            bind_dn: None,
+            bind_password: None,
+            base_dn: "ou=people,dc=example,dc=org".to_string(),
+            user_filter: "(uid={username})".to_string(),
+            email_attribute: "mail".to_string(),
+        }
+    }
+
+    // A pool that never connects, for cases rejected before any query
+    fn lazy_pool() -> PgPool {
+        PgPoolOptions::new().connect_lazy("postgres://localhost/dragonfruit_test").expect("valid database URL")
+    }
+
+    #[tokio::test]
+    async fn empty_password_is_rejected_without_binding() {
+        let backend = LdapBackend::new(config(), FakeDirectory::new(&[("alice", "uid=alice", Some("a@example.org"), "")]));
+
+        let result = backend.authenticate(&lazy_pool(), "alice", "").await;
+
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        assert_eq!(backend.directory.binds.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_user_is_unauthorized() {
+        let backend = LdapBackend::new(config(), FakeDirectory::new(&[]));
+
+        let result = backend.authenticate(&lazy_pool(), "nobody", "secret").await;
+
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_unauthorized() {
+        let backend = LdapBackend::new(config(), FakeDirectory::new(&[("alice", "uid=alice", Some("a@example.org"), "right")]));
+
+        let result = backend.authenticate(&lazy_pool(), "alice", "wrong").await;
+
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        assert_eq!(backend.directory.binds.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn ambiguous_filter_is_rejected_without_binding() {
+        let backend = LdapBackend::new(
+            config(),
+            FakeDirectory::new(&[
+                ("alice", "uid=alice,ou=staff", Some("a@example.org"), "secret"),
+                ("alice", "uid=alice,ou=contractors", Some("a2@example.org"), "secret"),
+            ]),
+        );
+
+        let result = backend.authenticate(&lazy_pool(), "alice", "secret").await;
+
+        assert!(matches!(result, Err(AppError::Internal(_))));
+        assert_eq!(backend.directory.binds.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn first_bind_maps_entry_to_a_local_user() {
+        let Some(pool) = test_pool().await else { return };
+
+        let login = format!("ldap{}", &Uuid::new_v4().simple().to_string()[..12]);
+        let dn = format!("uid={},ou=people,dc=example,dc=org", login);
+        let email = format!("{}@Example.org", login);
+        let backend = LdapBackend::new(
+            config(),
+            FakeDirectory::new(&[
+                (login.as_str(), dn.as_str(), Some(email.as_str()), "secret"),
+                ("nomail", "uid=nomail", None, "secret"),
+            ]),
+        );
+
+        let created = backend.authenticate(&pool, &login, "secret").await.expect("first sign-in");
+        assert_eq!(created.auth_source, "ldap");
+        let external_id = sqlx::query_scalar::<_, Option<String>>("SELECT external_id FROM users WHERE id = $1")
+            .bind(created.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(external_id.as_deref(), Some(dn.as_str()));
+        assert_eq!(created.username, login);
+        assert_eq!(created.email, email.to_lowercase());
+
+        // Later sign-ins find the same row by DN
+        let again = backend.authenticate(&pool, &login, "secret").await.expect("second sign-in");
+        assert_eq!(again.id, created.id);
+
+        // Without an email address there is nothing to create the account with
+        let result = backend.authenticate(&pool, "nomail", "secret").await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(created.id).execute(&pool).await.unwrap();
+    }
+}
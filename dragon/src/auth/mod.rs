@@ -1,3 +1,13 @@
+pub mod backend;
+pub mod devices;
+pub mod email_otp;
+pub mod identity;
 pub mod jwt;
+pub mod ldap;
+pub mod network;
 pub mod password;
-pub mod totp; 
\ No newline at end of file
+pub mod policy;
+pub mod session;
+pub mod strength;
+pub mod tokens;
+pub mod totp;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use ipnet::IpNet;
+use sqlx::{FromRow, PgPool};
+use std::{env, net::IpAddr};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+// Who manages an allowlist entry
+pub const MANAGED_BY_USER: &str = "user";
+pub const MANAGED_BY_ADMIN: &str = "admin";
+
+// Parse a CIDR range; a bare address is a single-host range
+pub fn parse_cidr(value: &str) -> Result<IpNet, AppError> {
+    let value = value.trim();
+    value.parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map(|net| net.trunc())
+        .map_err(|_| AppError::BadRequest(format!("Invalid CIDR range: {}", value)))
+}
+
+// Reverse proxies whose X-Forwarded-For header is believed
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    pub ranges: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    // Read TRUSTED_PROXIES, a comma-separated list of CIDR ranges (none by default)
+    pub fn from_env() -> Self {
+        let ranges = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|value| !value.trim().is_empty())
+            .filter_map(|value| match parse_cidr(value) {
+                Ok(net) => Some(net),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", value);
+                    None
+                }
+            })
+            .collect();
+
+        Self { ranges }
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|net| net.contains(&ip))
+    }
+
+    // Find the client address behind any trusted proxies. X-Forwarded-For is read
+    // from the right, stopping at the first hop that is not a trusted proxy, so a
+    // client cannot spoof its address by sending the header itself.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let mut client = peer.to_canonical();
+        let mut hops = forwarded_for
+            .unwrap_or_default()
+            .rsplit(',')
+            .map(str::trim)
+            .filter(|hop| !hop.is_empty());
+
+        while self.is_trusted(client) {
+            match hops.next().and_then(|hop| hop.parse::<IpAddr>().ok()) {
+                Some(hop) => client = hop.to_canonical(),
+                None => break,
+            }
+        }
+
+        client
+    }
+}
+
+#[derive(FromRow)]
+struct AllowlistRange {
+    cidr: String,
+    managed_by: String,
+}
+
+// A user's CIDR allowlists. An address must match the user's own list and the
+// admin policy, each only when it has entries; with no entries anything goes.
+#[derive(Debug, Clone, Default)]
+pub struct IpAllowlist {
+    pub user_ranges: Vec<IpNet>,
+    pub admin_ranges: Vec<IpNet>,
+}
+
+impl IpAllowlist {
+    pub async fn load(pool: &PgPool, user_id: Uuid) -> Result<Self, AppError> {
+        let rows = sqlx::query_as::<_, AllowlistRange>(
+            "SELECT cidr, managed_by FROM ip_allowlist_entries WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut allowlist = Self::default();
+        for row in rows {
+            let net = parse_cidr(&row.cidr)?;
+            if row.managed_by == MANAGED_BY_ADMIN {
+                allowlist.admin_ranges.push(net);
+            } else {
+                allowlist.user_ranges.push(net);
+            }
+        }
+
+        Ok(allowlist)
+    }
+
+    // Whether the address may be used. An unknown address only passes when no
+    // list applies.
+    pub fn allows(&self, ip: Option<IpAddr>) -> bool {
+        let matches = |ranges: &[IpNet]| {
+            ranges.is_empty() || ip.is_some_and(|ip| ranges.iter().any(|net| net.contains(&ip)))
+        };
+
+        matches(&self.user_ranges) && matches(&self.admin_ranges)
+    }
+
+    pub fn check(&self, ip: Option<IpAddr>) -> Result<(), AppError> {
+        if self.allows(ip) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("Access from this address is not allowed".to_string()))
+        }
+    }
+}
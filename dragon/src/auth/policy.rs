@@ -0,0 +1,185 @@
+use data_encoding::HEXUPPER;
+use sha1::{Digest, Sha1};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{auth::strength::estimate_entropy, errors::AppError};
+
+// What accounts with an unverified email address may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnverifiedPolicy {
+    Allow, // Full access while verification is pending
+    Block, // No sign-in and no API access until verified
+}
+
+impl UnverifiedPolicy {
+    // Read UNVERIFIED_ACCOUNTS ("allow" by default, or "block")
+    pub fn from_env() -> Self {
+        match env::var("UNVERIFIED_ACCOUNTS").as_deref() {
+            Ok("block") => Self::Block,
+            _ => Self::Allow,
+        }
+    }
+}
+
+// Hard cap so a huge input cannot make hashing expensive
+const MAX_PASSWORD_LENGTH: usize = 1024;
+
+// Requirements for account passwords
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub min_entropy_bits: f64,
+    pub breach_corpus: Option<PathBuf>, // Directory of HIBP range files named by hash prefix
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            min_entropy_bits: 50.0,
+            breach_corpus: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    // Read PASSWORD_MIN_LENGTH, PASSWORD_MIN_ENTROPY_BITS and BREACHED_PASSWORDS_DIR
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_length),
+            min_entropy_bits: env::var("PASSWORD_MIN_ENTROPY_BITS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_entropy_bits),
+            breach_corpus: env::var("BREACHED_PASSWORDS_DIR").ok().map(PathBuf::from),
+        }
+    }
+
+    // Check a password, collecting every reason it is rejected.
+    // `personal` holds values such as the username and email that must not appear in it.
+    pub async fn check(&self, password: &str, personal: &[&str]) -> Result<(), AppError> {
+        let mut reasons = Vec::new();
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            reasons.push(format!("Password must be at least {} characters long", self.min_length));
+        }
+
+        if length > MAX_PASSWORD_LENGTH {
+            reasons.push(format!("Password must be at most {} characters long", MAX_PASSWORD_LENGTH));
+        }
+
+        if estimate_entropy(password) < self.min_entropy_bits {
+            reasons.push("Password is too easy to guess".to_string());
+        }
+
+        let lowered = password.to_lowercase();
+        let contains_personal = personal.iter()
+            .map(|value| value.split('@').next().unwrap_or(value).to_lowercase())
+            .any(|value| value.chars().count() >= 3 && lowered.contains(&value));
+
+        if contains_personal {
+            reasons.push("Password must not contain your username or email".to_string());
+        }
+
+        if let Some(corpus) = &self.breach_corpus {
+            if is_breached(corpus, password).await? {
+                reasons.push("Password has appeared in a known data breach".to_string());
+            }
+        }
+
+        if reasons.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation("Password does not meet requirements".to_string(), reasons))
+        }
+    }
+}
+
+// Look the password up in a directory of HIBP-format range files: each file is
+// named after the first five hex digits of the SHA-1 and lists "SUFFIX:COUNT" lines
+async fn is_breached(corpus: &Path, password: &str) -> Result<bool, AppError> {
+    let digest = HEXUPPER.encode(&Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = digest.split_at(5);
+
+    let mut contents = None;
+    for name in [prefix.to_string(), format!("{}.txt", prefix)] {
+        match tokio::fs::read_to_string(corpus.join(name)).await {
+            Ok(text) => {
+                contents = Some(text);
+                break;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(AppError::Internal(format!("Failed to read breach corpus: {}", e))),
+        }
+    }
+
+    let Some(contents) = contents else {
+        return Ok(false);
+    };
+
+    // Padded range responses contain fake entries with a count of zero
+    Ok(contents.lines().any(|line| {
+        let mut parts = line.trim().splitn(2, ':');
+        let hash = parts.next().unwrap_or_default();
+        let count = parts.next().and_then(|c| c.trim().parse::<u64>().ok()).unwrap_or(1);
+        hash.eq_ignore_ascii_case(suffix) && count > 0
+    }))
+}
+
+// Who may create an account through POST /auth/register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Open,       // Anyone who can reach the server
+    InviteOnly, // Only with a valid invitation token
+    Disabled,   // Nobody; accounts come from LDAP or admins
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistrationPolicy {
+    pub mode: RegistrationMode,
+    pub allowed_domains: Vec<String>, // Empty means any domain
+}
+
+impl RegistrationPolicy {
+    // Read REGISTRATION_MODE ("open", "invite" or "disabled") and ALLOWED_EMAIL_DOMAINS
+    pub fn from_env() -> Self {
+        let mode = match env::var("REGISTRATION_MODE").as_deref() {
+            Ok("invite") => RegistrationMode::InviteOnly,
+            Ok("disabled") => RegistrationMode::Disabled,
+            _ => RegistrationMode::Open,
+        };
+
+        let allowed_domains = env::var("ALLOWED_EMAIL_DOMAINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+
+        Self { mode, allowed_domains }
+    }
+
+    // Whether an email address is in one of the allowed domains
+    pub fn email_allowed(&self, email: &str) -> bool {
+        if self.allowed_domains.is_empty() {
+            return true;
+        }
+
+        match email.rsplit_once('@') {
+            Some((_, domain)) => {
+                let domain = domain.trim().to_lowercase();
+                self.allowed_domains.contains(&domain)
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use axum::http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderMap, HeaderValue, Method,
+};
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::env;
+
+use crate::errors::AppError;
+
+// Cookie holding the JWT in cookie mode
+pub const SESSION_COOKIE: &str = "dragon_session";
+
+// Header carrying the CSRF token on state-changing requests
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// How the web client holds its session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionMode {
+    Bearer, // The token is returned in the body and sent in the Authorization header
+    Cookie, // The token lives in an HttpOnly cookie; writes need a CSRF token
+}
+
+impl SessionMode {
+    // Read SESSION_MODE ("bearer" by default, or "cookie")
+    pub fn from_env() -> Self {
+        match env::var("SESSION_MODE").as_deref() {
+            Ok("cookie") => Self::Cookie,
+            _ => Self::Bearer,
+        }
+    }
+}
+
+// A newly issued token as the client should receive it
+#[derive(Debug, Default)]
+pub struct IssuedSession {
+    pub headers: HeaderMap,
+    pub token: Option<String>,      // Only in bearer mode
+    pub csrf_token: Option<String>, // Only in cookie mode
+}
+
+impl IssuedSession {
+    // Hand out a token according to SESSION_MODE
+    pub fn new(token: String, max_age_secs: i64) -> Result<Self, AppError> {
+        if SessionMode::from_env() == SessionMode::Bearer {
+            return Ok(Self { token: Some(token), ..Default::default() });
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(SET_COOKIE, session_cookie(&token, max_age_secs)?);
+
+        Ok(Self {
+            headers,
+            token: None,
+            csrf_token: Some(csrf_token(&token)?),
+        })
+    }
+}
+
+// Set-Cookie value for the session. SESSION_COOKIE_SAMESITE ("Strict" by default)
+// and SESSION_COOKIE_SECURE ("true" by default) allow local HTTP setups.
+fn session_cookie(value: &str, max_age_secs: i64) -> Result<HeaderValue, AppError> {
+    let same_site = env::var("SESSION_COOKIE_SAMESITE").unwrap_or_else(|_| "Strict".to_string());
+    let secure = env::var("SESSION_COOKIE_SECURE").map(|v| v != "false").unwrap_or(true);
+
+    let mut cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite={}; Max-Age={}",
+        SESSION_COOKIE, value, same_site, max_age_secs
+    );
+    if secure {
+        cookie.push_str("; Secure");
+    }
+
+    HeaderValue::from_str(&cookie)
+        .map_err(|e| AppError::Internal(format!("Invalid session cookie: {}", e)))
+}
+
+// Headers that remove the session cookie
+pub fn clear_session_cookie() -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(SET_COOKIE, session_cookie("", 0)?);
+    Ok(headers)
+}
+
+// The session token from the cookie header, when cookie mode is on
+pub fn session_from_cookie(headers: &HeaderMap) -> Option<String> {
+    if SessionMode::from_env() != SessionMode::Cookie {
+        return None;
+    }
+
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, value)| *name == SESSION_COOKIE && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+// CSRF tokens are a MAC of the session token, so they need no storage and die with the session
+fn csrf_mac(session_token: &str) -> Result<Hmac<Sha256>, AppError> {
+    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_secret.as_bytes())
+        .map_err(|e| AppError::Internal(format!("HMAC error: {}", e)))?;
+    mac.update(format!("csrf:{}", session_token).as_bytes());
+    Ok(mac)
+}
+
+pub fn csrf_token(session_token: &str) -> Result<String, AppError> {
+    Ok(BASE64URL_NOPAD.encode(&csrf_mac(session_token)?.finalize().into_bytes()))
+}
+
+// Requests authenticated by cookie must prove they come from our client before changing anything
+pub fn check_csrf(method: &Method, headers: &HeaderMap, session_token: &str) -> Result<(), AppError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+
+    let provided = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| BASE64URL_NOPAD.decode(value.as_bytes()).ok())
+        .ok_or_else(|| AppError::Forbidden("Missing CSRF token".to_string()))?;
+
+    csrf_mac(session_token)?
+        .verify_slice(&provided)
+        .map_err(|_| AppError::Forbidden("Invalid CSRF token".to_string()))
+}
@@ -0,0 +1,285 @@
+// Password strength estimation in the style of zxcvbn: the password is covered by
+// the cheapest sequence of guessable patterns (common passwords and words, keyboard
+// runs, sequences, repeats and dates), with any remaining characters brute-forced.
+
+// Most common passwords and password words, roughly most common first. A word's
+// position is how many guesses an attacker working down the list needs.
+const COMMON_WORDS: &[&str] = &[
+    "password", "123456", "qwerty", "letmein", "welcome", "admin", "iloveyou", "monkey", "dragon",
+    "abc123", "football", "baseball", "master", "login", "princess", "sunshine", "shadow", "superman",
+    "batman", "trustno1", "passw0rd", "hello", "freedom", "whatever", "starwars", "secret", "access",
+    "mustang", "michael", "jessica", "charlie", "jordan", "hunter", "killer", "soccer", "hockey",
+    "ranger", "buster", "thomas", "robert", "daniel", "andrew", "joshua", "matthew", "jennifer",
+    "ashley", "nicole", "summer", "winter", "spring", "autumn", "flower", "cheese", "cookie",
+    "pepper", "ginger", "orange", "banana", "computer", "internet", "service", "system", "server",
+    "default", "changeme", "temp", "test", "guest", "user", "root", "love", "lovely", "angel",
+    "baby", "family", "friend", "happy", "money", "god", "jesus", "blessed", "mother", "father",
+    "sister", "brother", "purple", "yellow", "silver", "golden", "diamond", "tiger", "lion", "eagle",
+    "falcon", "phoenix", "wizard", "magic", "ninja", "pirate", "zombie", "matrix", "gamer", "player",
+    "music", "guitar", "london", "paris", "berlin", "america", "canada", "chicago", "boston",
+    "dallas", "liverpool", "arsenal", "chelsea", "barcelona", "company", "office", "work", "school",
+    "college", "student", "teacher", "monday", "tuesday", "wednesday", "thursday",
+    "friday", "saturday", "sunday", "january", "february", "march", "april", "may", "june", "july",
+    "august", "september", "october", "november", "december", "qwertyuiop", "asdfgh",
+    "zxcvbn", "abcdef", "abcd", "pass", "word", "secure", "security", "private", "vault", "dragonfruit",
+];
+
+// Keyboard rows, each run along in either direction
+const KEYBOARD_ROWS: &[&str] = &[
+    "`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./", "~!@#$%^&*()_+", "1qaz2wsx3edc4rfv5tgb6yhn",
+];
+
+// Characters commonly substituted for letters
+const LEET: &[(char, char)] = &[
+    ('4', 'a'), ('@', 'a'), ('8', 'b'), ('3', 'e'), ('6', 'g'), ('1', 'i'), ('!', 'i'),
+    ('0', 'o'), ('5', 's'), ('$', 's'), ('7', 't'), ('+', 't'), ('2', 'z'),
+];
+
+// Longest substring considered as a single pattern
+const MAX_PATTERN_LENGTH: usize = 32;
+
+// Inputs beyond this are rejected by length anyway; don't spend time on them
+const MAX_ESTIMATED_LENGTH: usize = 1024;
+
+// Years an attacker tries first
+const MIN_YEAR: u32 = 1900;
+const MAX_YEAR: u32 = 2049;
+
+// Estimated guessing entropy in bits
+pub fn estimate_entropy(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().take(MAX_ESTIMATED_LENGTH).collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let brute_force_bits = f64::from(pool_size(&chars)).log2();
+    let lowered: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    if lowered.len() != chars.len() {
+        // Case folding changed the length; fall back to brute force alone
+        return brute_force_bits * chars.len() as f64;
+    }
+
+    // best[j] is the cheapest cover of the first j characters, in bits
+    let mut best = vec![0.0; chars.len() + 1];
+    for end in 1..=chars.len() {
+        best[end] = best[end - 1] + brute_force_bits;
+
+        for start in end.saturating_sub(MAX_PATTERN_LENGTH)..end {
+            if let Some(guesses) = pattern_guesses(&chars[start..end], &lowered[start..end]) {
+                best[end] = f64::min(best[end], best[start] + guesses.log2());
+            }
+        }
+    }
+
+    best[chars.len()]
+}
+
+// Characters an attacker would have to try per position
+fn pool_size(chars: &[char]) -> u32 {
+    let mut pool = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) { pool += 26; }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) { pool += 26; }
+    if chars.iter().any(|c| c.is_ascii_digit()) { pool += 10; }
+    if chars.iter().any(|c| c.is_ascii() && !c.is_ascii_alphanumeric()) { pool += 33; }
+    if chars.iter().any(|c| !c.is_ascii()) { pool += 100; }
+    pool
+}
+
+// Guesses needed for a substring that forms a known pattern, taking the cheapest
+fn pattern_guesses(original: &[char], lowered: &[char]) -> Option<f64> {
+    if original.len() < 3 {
+        return None;
+    }
+
+    [
+        dictionary_guesses(original, lowered),
+        keyboard_guesses(lowered),
+        sequence_guesses(lowered),
+        repeat_guesses(original),
+        date_guesses(original),
+    ]
+    .into_iter()
+    .flatten()
+    .reduce(f64::min)
+}
+
+fn word_rank(word: &str) -> Option<usize> {
+    COMMON_WORDS.iter().position(|common| *common == word).map(|rank| rank + 1)
+}
+
+// Common passwords and words, also reversed, in mixed case or with l33t substitutions
+fn dictionary_guesses(original: &[char], lowered: &[char]) -> Option<f64> {
+    let word: String = lowered.iter().collect();
+    let unleet: String = lowered
+        .iter()
+        .map(|c| LEET.iter().find(|(from, _)| from == c).map_or(*c, |(_, to)| *to))
+        .collect();
+    let reversed: String = word.chars().rev().collect();
+
+    let (rank, variations) = if let Some(rank) = word_rank(&word) {
+        (rank, 1.0)
+    } else if let Some(rank) = word_rank(&reversed) {
+        (rank, 2.0)
+    } else if let Some(rank) = word_rank(&unleet) {
+        let substituted = lowered.iter().zip(unleet.chars()).filter(|(a, b)| **a != *b).count();
+        (rank, 2f64.powi(substituted as i32))
+    } else {
+        return None;
+    };
+
+    Some(rank as f64 * variations * case_variations(original))
+}
+
+// Extra guesses for the capitalisation of a word: all lower, all upper or
+// only the first letter cost little; anything else costs per uppercase letter
+fn case_variations(original: &[char]) -> f64 {
+    let letters: Vec<&char> = original.iter().filter(|c| c.is_alphabetic()).collect();
+    let upper = letters.iter().filter(|c| c.is_uppercase()).count();
+
+    if upper == 0 {
+        1.0
+    } else if upper == letters.len() || (upper == 1 && letters[0].is_uppercase()) {
+        2.0
+    } else {
+        2f64.powi(upper.min(letters.len() - upper).max(1) as i32 + 1)
+    }
+}
+
+// Runs along a keyboard row, such as "qwerty" or "09876"
+fn keyboard_guesses(lowered: &[char]) -> Option<f64> {
+    let run: String = lowered.iter().collect();
+    let reversed: String = run.chars().rev().collect();
+
+    KEYBOARD_ROWS
+        .iter()
+        .any(|row| row.contains(&run) || row.contains(&reversed))
+        .then_some(40.0 * lowered.len() as f64)
+}
+
+// Alphabetic or numeric sequences with a step of one, such as "abcd" or "9876"
+fn sequence_guesses(lowered: &[char]) -> Option<f64> {
+    let step = lowered[1] as i64 - lowered[0] as i64;
+    if step.abs() != 1 || lowered.windows(2).any(|pair| pair[1] as i64 - pair[0] as i64 != step) {
+        return None;
+    }
+
+    let base = if lowered[0].is_ascii_digit() { 10.0 } else { 26.0 };
+    let direction = if step < 0 { 2.0 } else { 1.0 };
+    Some(base * direction * lowered.len() as f64)
+}
+
+// One character repeated, such as "aaaa"
+fn repeat_guesses(original: &[char]) -> Option<f64> {
+    original
+        .iter()
+        .all(|c| *c == original[0])
+        .then(|| f64::from(pool_size(&original[..1])) * original.len() as f64)
+}
+
+// Years, and dates written as digits with or without separators
+fn date_guesses(original: &[char]) -> Option<f64> {
+    let text: String = original.iter().collect();
+    let year_guesses = f64::from(MAX_YEAR - MIN_YEAR + 1);
+
+    if text.len() == 4 && text.chars().all(|c| c.is_ascii_digit()) {
+        return text.parse().ok().filter(|year| is_year(*year)).map(|_| year_guesses);
+    }
+
+    let parts: Vec<&str> = match text.chars().find(|c| matches!(c, '-' | '/' | '.' | ' ')) {
+        Some(separator) => text.split(separator).collect(),
+        None if text.chars().all(|c| c.is_ascii_digit()) => return digit_date_guesses(&text, year_guesses),
+        None => return None,
+    };
+
+    let numbers: Vec<u32> = parts
+        .iter()
+        .filter(|part| (1..=4).contains(&part.len()) && part.chars().all(|c| c.is_ascii_digit()))
+        .filter_map(|part| part.parse().ok())
+        .collect();
+
+    (numbers.len() == 3 && parts.len() == 3 && is_date(&numbers)).then_some(365.0 * year_guesses * 4.0)
+}
+
+// Dates without separators: DDMMYY, MMDDYY, YYMMDD and the same with four-digit years
+fn digit_date_guesses(text: &str, year_guesses: f64) -> Option<f64> {
+    let splits: &[[usize; 3]] = match text.len() {
+        6 => &[[2, 2, 2]],
+        8 => &[[2, 2, 4], [4, 2, 2]],
+        _ => return None,
+    };
+
+    splits
+        .iter()
+        .any(|lengths| {
+            let mut rest = text;
+            let numbers: Vec<u32> = lengths
+                .iter()
+                .map(|len| {
+                    let (part, tail) = rest.split_at(*len);
+                    rest = tail;
+                    part.parse().unwrap_or(0)
+                })
+                .collect();
+            is_date(&numbers)
+        })
+        .then_some(365.0 * year_guesses)
+}
+
+fn is_year(year: u32) -> bool {
+    (MIN_YEAR..=MAX_YEAR).contains(&year)
+}
+
+// Whether three numbers read as a day, month and year in any common order
+fn is_date(numbers: &[u32]) -> bool {
+    let valid = |day: u32, month: u32, year: u32| {
+        (1..=31).contains(&day) && (1..=12).contains(&month) && (year < 100 || is_year(year))
+    };
+    let (a, b, c) = (numbers[0], numbers[1], numbers[2]);
+
+    valid(a, b, c) || valid(b, a, c) || valid(c, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_BITS: f64 = 50.0;
+
+    #[test]
+    fn common_passwords_with_decoration_are_weak() {
+        for password in [
+            "Password123!", "password", "P@ssw0rd2024", "Qwerty123456", "letmein!letmein",
+            "drowssap12345", "Summer2023!!", "iloveyou1990", "Dragon01011990",
+        ] {
+            let bits = estimate_entropy(password);
+            assert!(bits < MIN_BITS, "{} scored {:.1} bits", password, bits);
+        }
+    }
+
+    #[test]
+    fn keyboard_sequence_repeat_and_date_patterns_are_weak() {
+        for password in ["qwertyuiop123", "1qaz2wsx3edc", "abcdefghijkl", "aaaaaaaaaaaaaaaa", "01/02/1990", "19900102"] {
+            let bits = estimate_entropy(password);
+            assert!(bits < 40.0, "{} scored {:.1} bits", password, bits);
+        }
+    }
+
+    #[test]
+    fn random_and_passphrase_passwords_are_strong() {
+        for password in [
+            "hT9#kq2!Lm@4vZ", "correct horse battery staple", "Correct-Horse-Battery-9",
+            "vq7Rz2pLx9Wm", "Gl4c13r-M0th-Quill",
+        ] {
+            let bits = estimate_entropy(password);
+            assert!(bits >= MIN_BITS, "{} scored {:.1} bits", password, bits);
+        }
+    }
+
+    #[test]
+    fn patterns_cost_less_than_brute_force() {
+        assert!(estimate_entropy("password") < estimate_entropy("pxqsvjrd"));
+        assert!(estimate_entropy("Password") > estimate_entropy("password"));
+        assert!(estimate_entropy("p4ssw0rd") > estimate_entropy("password"));
+        assert_eq!(estimate_entropy(""), 0.0);
+    }
+}
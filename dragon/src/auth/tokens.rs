@@ -0,0 +1,89 @@
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+// Purposes for single-use tokens stored in user_tokens
+pub const EMAIL_VERIFICATION: &str = "email_verification";
+pub const PASSWORD_RESET: &str = "password_reset";
+pub const EMAIL_CHANGE: &str = "email_change";               // Sent to the new address
+pub const EMAIL_CHANGE_CANCEL: &str = "email_change_cancel"; // Sent to the old address
+
+// Generate a random URL-safe token
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+// Hash a token for storage; only the hash ever reaches the database
+pub fn hash_token(token: &str) -> String {
+    HEXLOWER.encode(&Sha256::digest(token.as_bytes()))
+}
+
+// Create a token for a user and return the raw value to send to them
+pub async fn issue_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    purpose: &str,
+    lifetime: Duration,
+) -> Result<String, AppError> {
+    let token = generate_token();
+    let expires_at = OffsetDateTime::now_utc() + lifetime;
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(hash_token(&token))
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
+// Mark a token as used and return its user, failing if it is unknown, used or expired
+pub async fn consume_token<'e, E: PgExecutor<'e>>(
+    executor: E,
+    purpose: &str,
+    token: &str,
+) -> Result<Uuid, AppError> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE user_tokens SET used_at = now()
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(purpose)
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired token".to_string()))
+}
+
+// Invalidate every outstanding token of a purpose for a user
+pub async fn revoke_tokens<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: Uuid,
+    purpose: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE user_tokens SET used_at = now() WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
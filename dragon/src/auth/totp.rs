@@ -9,6 +9,14 @@ use crate::errors::AppError;
 // Character set for TOTP codes (digits only)
 const CHARSET: &[u8] = b"0123456789";
 
+// Generate a random numeric one-time code, e.g. for email delivery
+pub fn generate_numeric_code(digits: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..digits)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
 // Generate a random TOTP secret
 pub fn generate_totp_secret(app_name: &str, account_name: &str) -> Result<(String, String), AppError> {
     // Generate a random 20-byte secret
@@ -3,6 +3,50 @@ use std::env;
 
 pub type DbPool = PgPool;
 
+// Every column the code reads or writes, by table. Extend this with each migration
+// so a server pointed at an older or hand-edited database refuses to start.
+const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    ("users", &[
+        "id", "username", "email", "pending_email", "password_hash", "auth_source", "external_id",
+        "role", "disabled", "totp_secret", "totp_enabled", "totp_required", "email_otp_enabled",
+        "password_reset_required", "email_verified", "token_version", "deletion_requested_at",
+        "deletion_scheduled_for", "created_at", "updated_at", "last_login",
+    ]),
+    ("categories", &[
+        "id", "user_id", "name", "description", "parent_id", "position", "color", "icon", "collapsed",
+        "created_at", "updated_at",
+    ]),
+    ("credentials", &[
+        "id", "user_id", "category_id", "item_type", "name", "website", "username", "password",
+        "payload", "metadata", "notes", "created_at", "updated_at",
+    ]),
+    ("user_tokens", &["id", "user_id", "purpose", "token_hash", "expires_at", "used_at", "created_at"]),
+    ("account_deletions", &["id", "user_id", "requested_at", "deleted_at"]),
+    ("admin_audit_log", &["id", "admin_id", "target_user_id", "action", "details", "created_at"]),
+    ("invitations", &["id", "token_hash", "email", "created_by", "used_by", "expires_at", "used_at", "created_at"]),
+    ("login_events", &[
+        "id", "user_id", "identifier", "success", "ip", "ip_range", "user_agent", "factor",
+        "failure_reason", "created_at",
+    ]),
+    ("ip_allowlist_entries", &["id", "user_id", "cidr", "description", "managed_by", "created_by", "created_at"]),
+    ("trusted_devices", &["id", "user_id", "token_hash", "name", "ip", "expires_at", "last_used_at", "created_at"]),
+    ("email_otp_codes", &["id", "user_id", "code_hash", "attempts", "expires_at", "used_at", "created_at"]),
+    ("device_authorizations", &[
+        "id", "device_code_hash", "user_code_hash", "client_id", "device_name", "ip", "user_agent",
+        "status", "user_id", "interval_secs", "last_polled_at", "decided_at", "expires_at", "created_at",
+    ]),
+    ("trash_items", &["id", "user_id", "item_type", "item_id", "data", "deleted_at"]),
+    ("credential_fields", &[
+        "id", "credential_id", "position", "label", "field_type", "value", "created_at", "updated_at",
+    ]),
+    ("password_history", &["id", "credential_id", "password", "replaced_at"]),
+    ("revisions", &[
+        "id", "user_id", "item_type", "item_id", "version", "changed_by", "changed_fields", "data",
+        "rollback_of", "created_at",
+    ]),
+    ("revoked_tokens", &["token_id", "user_id", "expires_at", "revoked_at"]),
+];
+
 pub async fn create_pool() -> DbPool {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     
@@ -13,11 +57,42 @@ pub async fn create_pool() -> DbPool {
         .expect("Failed to create database pool")
 }
 
-pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::Error> {
+pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
     sqlx::migrate!("./migrations")
         .run(pool)
         .await?;
     
     println!("Migrations applied successfully");
     Ok(())
-} 
\ No newline at end of file
+}
+
+// A migrated database from TEST_DATABASE_URL for tests that need one, or None to skip them
+#[cfg(test)]
+pub async fn test_pool() -> Option<PgPool> {
+    let url = env::var("TEST_DATABASE_URL").ok()?;
+    let pool = PgPool::connect(&url).await.expect("test database");
+    run_migrations(&pool).await.expect("migrations");
+    Some(pool)
+}
+
+// List required columns ("table.column") that the live schema does not have
+pub async fn missing_columns(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let present: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT table_name::text, column_name::text
+        FROM information_schema.columns
+        WHERE table_schema = current_schema()
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let missing = REQUIRED_COLUMNS
+        .iter()
+        .flat_map(|(table, columns)| columns.iter().map(move |column| (*table, *column)))
+        .filter(|(table, column)| !present.iter().any(|(t, c)| t == table && c == column))
+        .map(|(table, column)| format!("{}.{}", table, column))
+        .collect();
+
+    Ok(missing)
+}
@@ -11,11 +11,13 @@ use std::fmt;
 pub enum AppError {
     // Authentication errors
     Unauthorized(String),
+    Forbidden(String),
     
     // Client errors
     BadRequest(String),
     NotFound(String),
     Conflict(String),
+    Validation(String, Vec<String>), // Message and the individual reasons
     
     // Server errors
     Internal(String),
@@ -26,9 +28,11 @@ impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            Self::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             Self::BadRequest(msg) => write!(f, "Bad request: {}", msg),
             Self::NotFound(msg) => write!(f, "Not found: {}", msg),
             Self::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            Self::Validation(msg, reasons) => write!(f, "Validation failed: {} ({})", msg, reasons.join("; ")),
             Self::Internal(msg) => write!(f, "Internal server error: {}", msg),
             Self::Database(err) => write!(f, "Database error: {}", err),
         }
@@ -37,11 +41,25 @@ impl fmt::Display for AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        // Validation errors also list every reason the input was rejected
+        if let Self::Validation(msg, reasons) = self {
+            let body = Json(json!({
+                "error": {
+                    "message": msg,
+                    "reasons": reasons,
+                }
+            }));
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+        }
+
         let (status, error_message) = match self {
             Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            Self::Validation(msg, _) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
             Self::Internal(msg) => {
                 eprintln!("Internal server error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
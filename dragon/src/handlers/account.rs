@@ -0,0 +1,510 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use sqlx::PgPool;
+use std::env;
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    auth::{
+        backend::backend_from_env,
+        devices::revoke_trusted_devices,
+        identity::{normalize_email, normalize_identifier},
+        jwt::{create_token, TokenConfig},
+        password::{hash_password, verify_password},
+        policy::{PasswordPolicy, RegistrationPolicy},
+        session::IssuedSession,
+        tokens::{
+            consume_token, issue_token, revoke_tokens, EMAIL_CHANGE, EMAIL_CHANGE_CANCEL,
+            EMAIL_VERIFICATION, PASSWORD_RESET,
+        },
+        totp::verify_totp,
+    },
+    errors::AppError,
+    mail::{frontend_url, send_best_effort, Email},
+    middleware::auth::AuthUser,
+    models::user::{
+        ChangeEmail, ChangePassword, ConfirmPasswordReset, EmailRequest, Reauthenticate, User, UserResponse,
+        VerifyEmail,
+    },
+};
+
+// How long emailed links stay valid
+const VERIFICATION_TOKEN_LIFETIME: Duration = Duration::hours(24);
+const PASSWORD_RESET_TOKEN_LIFETIME: Duration = Duration::hours(1);
+const EMAIL_CHANGE_TOKEN_LIFETIME: Duration = Duration::hours(24);
+
+// Default and allowed days between a deletion request and the purge
+const DEFAULT_DELETION_GRACE_DAYS: i64 = 14;
+const MAX_DELETION_GRACE_DAYS: i64 = 3650;
+
+// Read ACCOUNT_DELETION_GRACE_DAYS. A value that is not a whole number of days
+// between 1 and MAX_DELETION_GRACE_DAYS is an error rather than an instant purge.
+pub fn deletion_grace_days() -> Result<i64, AppError> {
+    let Ok(value) = env::var("ACCOUNT_DELETION_GRACE_DAYS") else {
+        return Ok(DEFAULT_DELETION_GRACE_DAYS);
+    };
+
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|days| (1..=MAX_DELETION_GRACE_DAYS).contains(days))
+        .ok_or_else(|| {
+            AppError::Internal(format!(
+                "ACCOUNT_DELETION_GRACE_DAYS must be a whole number of days from 1 to {}",
+                MAX_DELETION_GRACE_DAYS
+            ))
+        })
+}
+
+// Confirm the caller still holds the account's password (and TOTP device, if enabled)
+pub async fn reauthenticate(pool: &PgPool, user: &User, proof: &Reauthenticate) -> Result<(), AppError> {
+    let backend = backend_from_env()?;
+    let verified = match backend.authenticate(pool, &user.username, &proof.password).await {
+        Ok(found) => found.id == user.id,
+        Err(AppError::NotFound(_)) | Err(AppError::Unauthorized(_)) => false,
+        Err(e) => return Err(e),
+    };
+
+    if !verified {
+        return Err(AppError::Unauthorized("Password is incorrect".to_string()));
+    }
+
+    if user.totp_enabled {
+        let totp_secret = user.totp_secret.as_ref()
+            .ok_or_else(|| AppError::Internal("TOTP secret not found".to_string()))?;
+
+        let totp_code = proof.totp_code.as_ref()
+            .ok_or_else(|| AppError::BadRequest("TOTP code required".to_string()))?;
+
+        if !verify_totp(totp_secret, totp_code, 30, 6)? {
+            return Err(AppError::Unauthorized("Invalid TOTP code".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+// Issue a verification token and email the confirmation link
+pub async fn send_verification_email(pool: &PgPool, user: &User) -> Result<(), AppError> {
+    // Only the most recent link should work
+    revoke_tokens(pool, user.id, EMAIL_VERIFICATION).await?;
+    let token = issue_token(pool, user.id, EMAIL_VERIFICATION, VERIFICATION_TOKEN_LIFETIME).await?;
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "Confirm your DragonFruit email address".to_string(),
+        body: format!(
+            "Hi {},\n\nConfirm your email address by opening this link within 24 hours:\n\n{}\n",
+            user.username,
+            frontend_url(&format!("/verify-email?token={}", token)),
+        ),
+    })
+    .await;
+
+    Ok(())
+}
+
+// Issue a password reset token and email the reset link
+pub async fn send_password_reset_email(pool: &PgPool, user: &User) -> Result<(), AppError> {
+    revoke_tokens(pool, user.id, PASSWORD_RESET).await?;
+    let token = issue_token(pool, user.id, PASSWORD_RESET, PASSWORD_RESET_TOKEN_LIFETIME).await?;
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "Reset your DragonFruit password".to_string(),
+        body: format!(
+            "Hi {},\n\nA password reset was requested for your account. \
+             Open this link within one hour to choose a new password:\n\n{}\n\n\
+             If you did not ask for this, you can ignore this email.\n",
+            user.username,
+            frontend_url(&format!("/reset-password?token={}", token)),
+        ),
+    })
+    .await;
+
+    Ok(())
+}
+
+// Confirm an email address with a token from the verification email
+pub async fn verify_email(
+    State(pool): State<PgPool>,
+    Json(payload): Json<VerifyEmail>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = consume_token(&pool, EMAIL_VERIFICATION, &payload.token).await?;
+
+    sqlx::query("UPDATE users SET email_verified = true, updated_at = now() WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "message": "Email address verified"
+        }))
+    ))
+}
+
+// Send a fresh verification email
+pub async fn resend_verification_email(
+    State(pool): State<PgPool>,
+    Json(payload): Json<EmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE lower(email) = lower($1) AND email_verified = false",
+    )
+    .bind(normalize_identifier(&payload.email))
+    .fetch_optional(&pool)
+    .await?;
+
+    if let Some(user) = user {
+        send_verification_email(&pool, &user).await?;
+    }
+
+    // Same answer either way so this cannot be used to probe for accounts
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "If the address belongs to an unverified account, a new link has been sent"
+        }))
+    ))
+}
+
+// Start a password reset by emailing a single-use link
+pub async fn request_password_reset(
+    State(pool): State<PgPool>,
+    Json(payload): Json<EmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // Directory accounts reset their password in the directory
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE lower(email) = lower($1) AND auth_source = 'local'",
+    )
+    .bind(normalize_identifier(&payload.email))
+    .fetch_optional(&pool)
+    .await?;
+
+    if let Some(user) = user {
+        send_password_reset_email(&pool, &user).await?;
+    }
+
+    // Same answer either way so this cannot be used to probe for accounts
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "If the address belongs to an account, a reset link has been sent"
+        }))
+    ))
+}
+
+// Finish a password reset and sign out every existing session
+pub async fn confirm_password_reset(
+    State(pool): State<PgPool>,
+    Json(payload): Json<ConfirmPasswordReset>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = consume_token(&mut *tx, PASSWORD_RESET, &payload.token).await?;
+
+    // A rejected password rolls back, so the token can be used again
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    PasswordPolicy::from_env()
+        .check(&payload.new_password, &[&user.username, &user.email])
+        .await?;
+
+    let password_hash = hash_password(&payload.new_password)?;
+
+    // Receiving the link also proves ownership of the address
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $1, email_verified = true, password_reset_required = false,
+            token_version = token_version + 1, updated_at = now()
+        WHERE id = $2
+        "#,
+    )
+    .bind(&password_hash)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    revoke_tokens(&mut *tx, user_id, PASSWORD_RESET).await?;
+
+    // Whoever reset the password should not inherit the old trusted devices
+    revoke_trusted_devices(&mut *tx, user_id).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "message": "Password has been reset"
+        }))
+    ))
+}
+
+// Change the account password after checking the current one
+pub async fn change_password(
+    State(pool): State<PgPool>,
+    auth_user: AuthUser,
+    Json(payload): Json<ChangePassword>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    // Directory accounts change their password in the directory
+    if user.auth_source != "local" {
+        return Err(AppError::BadRequest("This account's password is managed by the directory".to_string()));
+    }
+
+    if !verify_password(&payload.current_password, &user.password_hash)? {
+        return Err(AppError::Unauthorized("Current password is incorrect".to_string()));
+    }
+
+    PasswordPolicy::from_env()
+        .check(&payload.new_password, &[&user.username, &user.email])
+        .await?;
+
+    let password_hash = hash_password(&payload.new_password)?;
+
+    // Credential encryption keys come from the server secret, not this password
+    // (see crypto::encrypt_password), so the vault needs no re-encryption here.
+    // Bumping the token version signs out every other session.
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET password_hash = $1, token_version = token_version + 1, updated_at = now()
+        WHERE id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(&password_hash)
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await?;
+
+    // Any outstanding reset link and trusted device is for the old password
+    revoke_tokens(&pool, user.id, PASSWORD_RESET).await?;
+    revoke_trusted_devices(&pool, user.id).await?;
+
+    // Hand this session a token for the new version
+    let token_config = TokenConfig::default();
+    let token = create_token(user.id, user.token_version, &token_config)?;
+    let session = IssuedSession::new(token, token_config.expires_in)?;
+
+    Ok((
+        StatusCode::OK,
+        session.headers,
+        Json(serde_json::json!({
+            "user": UserResponse::from(user),
+            "token": session.token,
+            "csrf_token": session.csrf_token
+        }))
+    ))
+}
+
+// Schedule the account for deletion; signing in again before the purge cancels it
+pub async fn request_account_deletion(
+    State(pool): State<PgPool>,
+    auth_user: AuthUser,
+    Json(payload): Json<Reauthenticate>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    reauthenticate(&pool, &user, &payload).await?;
+
+    let grace_days = deletion_grace_days()?;
+    let scheduled_for = OffsetDateTime::now_utc() + Duration::days(grace_days);
+
+    // Sign out everywhere so only a fresh login (which cancels) can reach the vault
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET deletion_requested_at = now(), deletion_scheduled_for = $1,
+            token_version = token_version + 1, updated_at = now()
+        WHERE id = $2
+        "#,
+    )
+    .bind(scheduled_for)
+    .bind(user.id)
+    .execute(&pool)
+    .await?;
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "Your DragonFruit account will be deleted".to_string(),
+        body: format!(
+            "Hi {},\n\nYour account and every credential in it will be permanently deleted on {}.\n\n\
+             To keep your account, sign in again before then.\n",
+            user.username,
+            scheduled_for.date(),
+        ),
+    })
+    .await;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Account scheduled for deletion",
+            "deletion_scheduled_for": scheduled_for.to_string()
+        }))
+    ))
+}
+
+// Start an email change: confirm with the new address, offer a cancel link to the old one
+pub async fn request_email_change(
+    State(pool): State<PgPool>,
+    auth_user: AuthUser,
+    Json(payload): Json<ChangeEmail>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    reauthenticate(&pool, &user, &payload.proof).await?;
+
+    let new_email = normalize_email(&payload.new_email)?;
+    if new_email.to_lowercase() == user.email.to_lowercase() {
+        return Err(AppError::BadRequest("That is already your email address".to_string()));
+    }
+
+    if !RegistrationPolicy::from_env().email_allowed(&new_email) {
+        return Err(AppError::Forbidden("Email addresses in this domain are not allowed".to_string()));
+    }
+
+    let taken = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE lower(email) = lower($1))",
+    )
+    .bind(&new_email)
+    .fetch_one(&pool)
+    .await?;
+
+    if taken {
+        return Err(AppError::Conflict("Email already exists".to_string()));
+    }
+
+    sqlx::query("UPDATE users SET pending_email = $1, updated_at = now() WHERE id = $2")
+        .bind(&new_email)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    // Only the latest request's links should work
+    revoke_tokens(&pool, user.id, EMAIL_CHANGE).await?;
+    revoke_tokens(&pool, user.id, EMAIL_CHANGE_CANCEL).await?;
+    let confirm_token = issue_token(&pool, user.id, EMAIL_CHANGE, EMAIL_CHANGE_TOKEN_LIFETIME).await?;
+    let cancel_token = issue_token(&pool, user.id, EMAIL_CHANGE_CANCEL, EMAIL_CHANGE_TOKEN_LIFETIME).await?;
+
+    send_best_effort(Email {
+        to: new_email.clone(),
+        subject: "Confirm your new DragonFruit email address".to_string(),
+        body: format!(
+            "Hi {},\n\nConfirm this address for your DragonFruit account by opening this link \
+             within 24 hours:\n\n{}\n",
+            user.username,
+            frontend_url(&format!("/confirm-email?token={}", confirm_token)),
+        ),
+    })
+    .await;
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "Your DragonFruit email address is being changed".to_string(),
+        body: format!(
+            "Hi {},\n\nSomeone asked to change your account's email address to {}. \
+             If this wasn't you, cancel the change and sign out every session here:\n\n{}\n",
+            user.username,
+            new_email,
+            frontend_url(&format!("/cancel-email-change?token={}", cancel_token)),
+        ),
+    })
+    .await;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Check your new email address to confirm the change",
+            "pending_email": new_email
+        }))
+    ))
+}
+
+// Apply a pending email change with the token sent to the new address
+pub async fn confirm_email_change(
+    State(pool): State<PgPool>,
+    Json(payload): Json<VerifyEmail>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = consume_token(&mut *tx, EMAIL_CHANGE, &payload.token).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET email = pending_email, pending_email = NULL, email_verified = true, updated_at = now()
+        WHERE id = $1 AND pending_email IS NOT NULL
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("users_email_key") {
+            AppError::Conflict("Email already exists".to_string())
+        } else {
+            AppError::Database(e)
+        }
+    })?
+    .ok_or_else(|| AppError::BadRequest("No email change is pending".to_string()))?;
+
+    revoke_tokens(&mut *tx, user_id, EMAIL_CHANGE_CANCEL).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(UserResponse::from(user))))
+}
+
+// Cancel a pending email change from the old address and sign out every session
+pub async fn cancel_email_change(
+    State(pool): State<PgPool>,
+    Json(payload): Json<VerifyEmail>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let user_id = consume_token(&mut *tx, EMAIL_CHANGE_CANCEL, &payload.token).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET pending_email = NULL, token_version = token_version + 1, updated_at = now()
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    revoke_tokens(&mut *tx, user_id, EMAIL_CHANGE).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "message": "Email change cancelled and all sessions signed out"
+        }))
+    ))
+}
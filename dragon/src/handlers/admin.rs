@@ -0,0 +1,369 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        devices::revoke_trusted_devices,
+        network::{parse_cidr, MANAGED_BY_ADMIN},
+    },
+    errors::AppError,
+    handlers::{
+        account::send_password_reset_email,
+        security::{insert_allowlist_entry, list_allowlist_entries},
+    },
+    middleware::auth::AdminUser,
+    models::user::{
+        AdminAction, AdminUserResponse, CreateIpAllowlistEntry, SetRole, User, UserSearch, ROLE_ADMIN,
+        ROLE_USER,
+    },
+};
+
+// Page size limits for the user list
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// Record an admin action in the audit log
+async fn record_action(
+    pool: &PgPool,
+    admin: &AdminUser,
+    target_user_id: Option<Uuid>,
+    action: &str,
+    details: Option<String>,
+) -> Result<(), AppError> {
+    tracing::info!(
+        admin_id = %admin.user_id,
+        target_user_id = ?target_user_id,
+        action,
+        "Admin action"
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_audit_log (admin_id, target_user_id, action, details)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(admin.user_id)
+    .bind(target_user_id)
+    .bind(action)
+    .bind(details)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Load a user by ID for an admin action
+async fn fetch_user(pool: &PgPool, user_id: Uuid) -> Result<User, AppError> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+// Admins must not lock themselves out by accident
+fn reject_self(admin: &AdminUser, user_id: Uuid, action: &str) -> Result<(), AppError> {
+    if admin.user_id == user_id {
+        return Err(AppError::BadRequest(format!("You cannot {} your own account", action)));
+    }
+    Ok(())
+}
+
+// List users, optionally filtered by a username or email search
+pub async fn list_users(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Query(search): Query<UserSearch>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = search.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = search.offset.unwrap_or(0).max(0);
+
+    // Treat the search text literally inside the LIKE pattern
+    let pattern = search.q
+        .filter(|q| !q.trim().is_empty())
+        .map(|q| {
+            let escaped = q.trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{}%", escaped)
+        });
+
+    let users = sqlx::query_as::<_, User>(
+        r#"
+        SELECT * FROM users
+        WHERE $1::text IS NULL OR username ILIKE $1 OR email ILIKE $1
+        ORDER BY username
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&pattern)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await?;
+
+    record_action(&pool, &admin, None, "list_users", pattern).await?;
+
+    let users_response: Vec<AdminUserResponse> = users
+        .into_iter()
+        .map(AdminUserResponse::from)
+        .collect();
+
+    Ok((StatusCode::OK, Json(users_response)))
+}
+
+// Get one user's account state
+pub async fn get_user(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = fetch_user(&pool, user_id).await?;
+
+    record_action(&pool, &admin, Some(user_id), "view_user", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Disable an account and sign it out everywhere
+pub async fn disable_user(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    reject_self(&admin, user_id, "disable")?;
+    fetch_user(&pool, user_id).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET disabled = true, token_version = token_version + 1, updated_at = now()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    record_action(&pool, &admin, Some(user_id), "disable_user", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Re-enable a disabled account
+pub async fn enable_user(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET disabled = false, updated_at = now() WHERE id = $1 RETURNING *",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    record_action(&pool, &admin, Some(user_id), "enable_user", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Sign the user out and make them choose a new password through an emailed link
+pub async fn force_password_reset(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = fetch_user(&pool, user_id).await?;
+
+    if user.auth_source != "local" {
+        return Err(AppError::BadRequest("This account's password is managed by the directory".to_string()));
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET password_reset_required = true, token_version = token_version + 1, updated_at = now()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    revoke_trusted_devices(&pool, user_id).await?;
+    send_password_reset_email(&pool, &user).await?;
+
+    record_action(&pool, &admin, Some(user_id), "force_password_reset", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Require the user to enrol in TOTP before using the API again
+pub async fn require_totp(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET totp_required = true, updated_at = now() WHERE id = $1 RETURNING *",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    record_action(&pool, &admin, Some(user_id), "require_totp", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Lift a TOTP enrolment requirement
+pub async fn clear_totp_requirement(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET totp_required = false, updated_at = now() WHERE id = $1 RETURNING *",
+    )
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    record_action(&pool, &admin, Some(user_id), "clear_totp_requirement", None).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// Invalidate every token and trusted device issued to the user
+pub async fn revoke_user_tokens(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    sqlx::query("UPDATE users SET token_version = token_version + 1 WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await?;
+
+    revoke_trusted_devices(&pool, user_id).await?;
+
+    record_action(&pool, &admin, Some(user_id), "revoke_tokens", None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Grant or remove the admin role
+pub async fn set_user_role(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<SetRole>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.role != ROLE_USER && payload.role != ROLE_ADMIN {
+        return Err(AppError::BadRequest(format!("Unknown role: {}", payload.role)));
+    }
+    reject_self(&admin, user_id, "change the role of")?;
+    fetch_user(&pool, user_id).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET role = $1, updated_at = now() WHERE id = $2 RETURNING *",
+    )
+    .bind(&payload.role)
+    .bind(user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    record_action(&pool, &admin, Some(user_id), "set_role", Some(payload.role)).await?;
+
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(user))))
+}
+
+// List a user's allowlist entries, both their own and admin policy
+pub async fn get_user_ip_allowlist(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    let entries = list_allowlist_entries(&pool, user_id).await?;
+
+    record_action(&pool, &admin, Some(user_id), "view_ip_allowlist", None).await?;
+
+    Ok((StatusCode::OK, Json(entries)))
+}
+
+// Add a policy range the user cannot remove themselves
+pub async fn add_user_ip_allowlist_entry(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<CreateIpAllowlistEntry>,
+) -> Result<impl IntoResponse, AppError> {
+    fetch_user(&pool, user_id).await?;
+
+    let net = parse_cidr(&payload.cidr)?;
+    let entry = insert_allowlist_entry(&pool, user_id, MANAGED_BY_ADMIN, admin.user_id, &payload, net).await?;
+
+    record_action(&pool, &admin, Some(user_id), "add_ip_allowlist_entry", Some(entry.cidr.clone())).await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+// Remove any allowlist entry, including one that has locked the user out
+pub async fn remove_user_ip_allowlist_entry(
+    admin: AdminUser,
+    State(pool): State<PgPool>,
+    Path((user_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let cidr = sqlx::query_scalar::<_, String>(
+        "DELETE FROM ip_allowlist_entries WHERE id = $1 AND user_id = $2 RETURNING cidr",
+    )
+    .bind(entry_id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Allowlist entry not found".to_string()))?;
+
+    record_action(&pool, &admin, Some(user_id), "remove_ip_allowlist_entry", Some(cidr)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Most recent admin actions, newest first
+pub async fn get_audit_log(
+    _admin: AdminUser,
+    State(pool): State<PgPool>,
+    Query(search): Query<UserSearch>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = search.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = search.offset.unwrap_or(0).max(0);
+
+    let actions = sqlx::query_as::<_, AdminAction>(
+        "SELECT * FROM admin_audit_log ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(actions)))
+}
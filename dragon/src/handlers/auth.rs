@@ -1,113 +1,322 @@
 use axum::{
     extract::{Json, State},
     response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use sqlx::PgPool;
 use time::OffsetDateTime;
 
 use crate::{
     auth::{
-        jwt::{create_token, TokenConfig},
-        password::{hash_password, verify_password},
+        backend::backend_from_env,
+        devices::{trust_device, use_trusted_device},
+        email_otp::{send_email_code, verify_email_code},
+        identity::{normalize_email, normalize_username},
+        jwt::{create_token, TokenConfig, TokenSubject},
+        network::IpAllowlist,
+        password::hash_password,
+        policy::{PasswordPolicy, RegistrationMode, RegistrationPolicy, UnverifiedPolicy},
+        session::{clear_session_cookie, csrf_token, session_from_cookie, IssuedSession},
         totp::{generate_totp_secret, verify_totp},
     },
     errors::AppError,
+    handlers::{
+        account::{reauthenticate, send_verification_email},
+        invitation::claim_invitation,
+        security::{
+            record_failed_login, record_successful_login, LoginFailure, FACTOR_PASSWORD, FACTOR_PASSWORD_DEVICE,
+            FACTOR_PASSWORD_EMAIL, FACTOR_PASSWORD_TOTP, FAILURE_ACCOUNT_DISABLED, FAILURE_EMAIL_UNVERIFIED,
+            FAILURE_INTERNAL, FAILURE_INVALID_PASSWORD, FAILURE_INVALID_SECOND_FACTOR, FAILURE_IP_NOT_ALLOWED,
+            FAILURE_RESET_REQUIRED, FAILURE_SECOND_FACTOR_REQUIRED,
+        },
+    },
     models::user::{
-        User, CreateUser, LoginUser, UserResponse,
+        User, CreateUser, LoginUser, Reauthenticate, UserResponse,
     },
-    middleware::auth::AuthUser,
+    middleware::{auth::AuthUser, client::ClientInfo},
 };
 
+// Map a unique index violation on users to a readable conflict
+fn unique_violation(e: sqlx::Error) -> AppError {
+    if e.to_string().contains("duplicate key") {
+        if e.to_string().contains("users_email_key") {
+            AppError::Conflict("Email already exists".to_string())
+        } else if e.to_string().contains("users_username_key") {
+            AppError::Conflict("Username already exists".to_string())
+        } else {
+            AppError::Database(e)
+        }
+    } else {
+        AppError::Database(e)
+    }
+}
+
 // Register a new user
 pub async fn register(
     State(pool): State<PgPool>,
     Json(payload): Json<CreateUser>,
 ) -> Result<impl IntoResponse, AppError> {
+    let registration = RegistrationPolicy::from_env();
+    if registration.mode == RegistrationMode::Disabled {
+        return Err(AppError::Forbidden("Registration is disabled".to_string()));
+    }
+
+    // Canonical forms, so "Alice@x.com" and "alice@x.com" are the same account
+    let username = normalize_username(&payload.username)?;
+    let email = normalize_email(&payload.email)?;
+
+    // Enforce the password policy before touching the database
+    PasswordPolicy::from_env()
+        .check(&payload.password, &[&username, &email])
+        .await?;
+
     let password_hash = hash_password(&payload.password)?;
+
+    let mut tx = pool.begin().await?;
+
+    // In invite-only mode, claim the invitation in the same transaction as the account
+    let invitation = match (registration.mode, &payload.invite_token) {
+        (RegistrationMode::InviteOnly, None) => {
+            return Err(AppError::Forbidden("An invitation is required to register".to_string()));
+        }
+        (_, Some(invite_token)) => Some(claim_invitation(&mut tx, invite_token, &email).await?),
+        (_, None) => None,
+    };
+
+    // Invitations addressed to a specific email skip the domain allowlist
+    let addressed = invitation.as_ref().is_some_and(|i| i.email.is_some());
+    if !addressed && !registration.email_allowed(&email) {
+        return Err(AppError::Forbidden("Registration is not open to this email domain".to_string()));
+    }
     
     let user = sqlx::query_as::<_, User>(
         r#"
         INSERT INTO users (username, email, password_hash)
         VALUES ($1, $2, $3)
-        RETURNING id, username, email, password_hash, totp_secret, totp_enabled, created_at, updated_at, last_login
+        RETURNING id, username, email, pending_email, password_hash, auth_source, role, disabled, totp_secret, totp_enabled, totp_required, email_otp_enabled, password_reset_required, email_verified, token_version, deletion_scheduled_for, created_at, updated_at, last_login
         "#,
     )
-    .bind(&payload.username)
-    .bind(&payload.email)
+    .bind(&username)
+    .bind(&email)
     .bind(&password_hash)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| {
-        if e.to_string().contains("duplicate key") {
-            if e.to_string().contains("users_email_key") {
-                AppError::Conflict("Email already exists".to_string())
-            } else if e.to_string().contains("users_username_key") {
-                AppError::Conflict("Username already exists".to_string())
-            } else {
-                AppError::Database(e)
-            }
-        } else {
-            AppError::Database(e)
-        }
-    })?;
+    .map_err(unique_violation)?;
+
+    if let Some(invitation) = invitation {
+        sqlx::query("UPDATE invitations SET used_by = $1 WHERE id = $2")
+            .bind(user.id)
+            .bind(invitation.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    // Ask the user to confirm their address
+    send_verification_email(&pool, &user).await?;
 
     // Respond with user info
     Ok((StatusCode::CREATED, Json(UserResponse::from(user))))
 }
 
+// Check that a user who has proven who they are may sign in from this client.
+// Every way of getting a token goes through here.
+pub async fn check_account_access(pool: &PgPool, user: &User, client: &ClientInfo) -> Result<(), LoginFailure> {
+    let fail = |reason, message: &str| LoginFailure::new(reason, AppError::Unauthorized(message.to_string()));
+
+    if user.disabled {
+        return Err(fail(FAILURE_ACCOUNT_DISABLED, "Account disabled"));
+    }
+
+    // The account may only be reachable from listed networks
+    IpAllowlist::load(pool, user.id)
+        .await
+        .and_then(|allowlist| allowlist.check(client.ip))
+        .map_err(|e| LoginFailure::new(FAILURE_IP_NOT_ALLOWED, e))?;
+
+    // An administrator can require a reset before the next sign-in
+    if user.password_reset_required {
+        return Err(fail(FAILURE_RESET_REQUIRED, "Password reset required; use the link sent to your email"));
+    }
+
+    // Unverified accounts may be locked out until they confirm their email
+    if !user.email_verified && UnverifiedPolicy::from_env() == UnverifiedPolicy::Block {
+        return Err(fail(FAILURE_EMAIL_UNVERIFIED, "Email address not verified"));
+    }
+
+    Ok(())
+}
+
+// Check everything a login needs and return the user with the factors used
+async fn check_login(
+    pool: &PgPool,
+    payload: &LoginUser,
+    client: &ClientInfo,
+) -> Result<(User, &'static str), LoginFailure> {
+    let fail = |reason, message: &str| LoginFailure::new(reason, AppError::Unauthorized(message.to_string()));
+
+    // Check the password with the configured backend
+    let backend = backend_from_env().map_err(|e| LoginFailure::new(FAILURE_INTERNAL, e))?;
+    let user = backend
+        .authenticate(pool, &payload.identifier, &payload.password)
+        .await
+        .map_err(|e| LoginFailure::new(FAILURE_INVALID_PASSWORD, e))?;
+
+    check_account_access(pool, &user, client).await?;
+
+    // Accounts with a second factor need one of them, unless the device is trusted
+    if user.totp_enabled || user.email_otp_enabled {
+        let internal = |e| LoginFailure::new(FAILURE_INTERNAL, e);
+
+        if let Some(device_token) = &payload.device_token {
+            if use_trusted_device(pool, user.id, device_token, client).await.map_err(internal)? {
+                return Ok((user, FACTOR_PASSWORD_DEVICE));
+            }
+        }
+
+        if user.totp_enabled {
+            if let Some(totp_code) = &payload.totp_code {
+                let totp_secret = user.totp_secret.as_ref()
+                    .ok_or_else(|| internal(AppError::Internal("TOTP secret not found".to_string())))?;
+
+                if !verify_totp(totp_secret, totp_code, 30, 6).map_err(internal)? {
+                    return Err(fail(FAILURE_INVALID_SECOND_FACTOR, "Invalid TOTP code"));
+                }
+
+                return Ok((user, FACTOR_PASSWORD_TOTP));
+            }
+        }
+
+        if user.email_otp_enabled {
+            match &payload.email_code {
+                Some(email_code) => {
+                    if !verify_email_code(pool, user.id, email_code).await.map_err(internal)? {
+                        return Err(fail(FAILURE_INVALID_SECOND_FACTOR, "Invalid or expired email code"));
+                    }
+
+                    return Ok((user, FACTOR_PASSWORD_EMAIL));
+                }
+                // The password was right, so this is the moment to send a code
+                None => {
+                    send_email_code(pool, &user).await.map_err(internal)?;
+                    return Err(LoginFailure::new(
+                        FAILURE_SECOND_FACTOR_REQUIRED,
+                        AppError::BadRequest("Email code required; a code has been sent".to_string()),
+                    ));
+                }
+            }
+        }
+
+        return Err(LoginFailure::new(
+            FAILURE_SECOND_FACTOR_REQUIRED,
+            AppError::BadRequest("TOTP code required".to_string()),
+        ));
+    }
+
+    Ok((user, FACTOR_PASSWORD))
+}
+
 // Login a user
 pub async fn login(
     State(pool): State<PgPool>,
+    client: ClientInfo,
     Json(payload): Json<LoginUser>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Find user by username
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE username = $1",
-    )
-    .bind(&payload.username)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    // Every attempt lands in the login history, successful or not
+    let (user, factor) = match check_login(&pool, &payload, &client).await {
+        Ok(result) => result,
+        Err(failure) => {
+            record_failed_login(&pool, &payload.identifier, &client, failure.reason).await;
+            return Err(failure.error);
+        }
+    };
 
-    // Verify password
-    let password_verified = verify_password(&payload.password, &user.password_hash)?;
-    
-    if !password_verified {
-        return Err(AppError::Unauthorized("Invalid password".to_string()));
-    }
+    record_successful_login(&pool, &user, &client, factor).await?;
 
-    // If TOTP is enabled, verify the TOTP code
-    if user.totp_enabled {
-        let totp_secret = user.totp_secret.as_ref()
-            .ok_or_else(|| AppError::Internal("TOTP secret not found".to_string()))?;
-        
-        let totp_code = payload.totp_code
-            .ok_or_else(|| AppError::BadRequest("TOTP code required".to_string()))?;
-        
-        if !verify_totp(totp_secret, &totp_code, 30, 6)? {
-            return Err(AppError::Unauthorized("Invalid TOTP code".to_string()));
-        }
-    }
+    // Only a login that just passed a second factor can trust its device
+    let second_factor = factor == FACTOR_PASSWORD_TOTP || factor == FACTOR_PASSWORD_EMAIL;
+    let trusted_device = if payload.trust_device && second_factor {
+        Some(trust_device(&pool, user.id, payload.device_name.as_deref(), &client).await?)
+    } else {
+        None
+    };
+
+    // Signing in during the grace period cancels a pending account deletion
+    let deletion_cancelled = user.deletion_scheduled_for.is_some();
 
     // Update last login time
     let now = OffsetDateTime::now_utc();
-    sqlx::query("UPDATE users SET last_login = $1 WHERE id = $2")
-        .bind(now)
-        .bind(user.id)
-        .execute(&pool)
-        .await?;
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET last_login = $1, deletion_requested_at = NULL, deletion_scheduled_for = NULL
+        WHERE id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(now)
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await?;
 
     // Create JWT token
     let token_config = TokenConfig::default();
-    let token = create_token(user.id, &token_config)?;
+    let token = create_token(user.id, user.token_version, &token_config)?;
+
+    // In cookie mode the token goes into an HttpOnly cookie instead of the body
+    let session = IssuedSession::new(token, token_config.expires_in)?;
 
     // Return user and token
     Ok((
-        StatusCode::OK, 
+        StatusCode::OK,
+        session.headers,
         Json(serde_json::json!({
             "user": UserResponse::from(user),
-            "token": token
+            "token": session.token,
+            "csrf_token": session.csrf_token,
+            "deletion_cancelled": deletion_cancelled,
+            "device_token": trusted_device.as_ref().map(|(device_token, _)| device_token),
+            "trusted_device": trusted_device.as_ref().map(|(_, device)| device)
+        }))
+    ))
+}
+
+// End a session: the token it used stops working and the session cookie is
+// cleared. A request whose token is already invalid only has the cookie cleared.
+pub async fn logout(
+    auth_user: Result<AuthUser, AppError>,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Ok(AuthUser { user_id, token: TokenSubject { token_id: Some(token_id), expires_at, .. }, .. }) = auth_user {
+        let expires_at = OffsetDateTime::from_unix_timestamp(expires_at)
+            .map_err(|e| AppError::Internal(format!("Invalid token expiry: {}", e)))?;
+
+        sqlx::query(
+            "INSERT INTO revoked_tokens (token_id, user_id, expires_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&pool)
+        .await?;
+    }
+
+    Ok((StatusCode::NO_CONTENT, clear_session_cookie()?))
+}
+
+// CSRF token for the current cookie session, e.g. after a page reload
+pub async fn get_csrf_token(
+    _auth_user: AuthUser,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let session_token = session_from_cookie(&headers)
+        .ok_or_else(|| AppError::BadRequest("Not using a session cookie".to_string()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "csrf_token": csrf_token(&session_token)?
         }))
     ))
 }
@@ -175,6 +384,63 @@ pub async fn enable_totp(
     ))
 }
 
+// Turn on emailed codes as a second factor
+pub async fn enable_email_otp(
+    State(pool): State<PgPool>,
+    auth_user: AuthUser,
+    Json(proof): Json<Reauthenticate>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    reauthenticate(&pool, &user, &proof).await?;
+
+    // Codes sent to an unconfirmed address could lock the user out
+    if !user.email_verified {
+        return Err(AppError::BadRequest("Verify your email address first".to_string()));
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET email_otp_enabled = true, updated_at = now() WHERE id = $1 RETURNING *",
+    )
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(UserResponse::from(user))))
+}
+
+// Turn off emailed codes
+pub async fn disable_email_otp(
+    State(pool): State<PgPool>,
+    auth_user: AuthUser,
+    Json(proof): Json<Reauthenticate>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    reauthenticate(&pool, &user, &proof).await?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET email_otp_enabled = false, updated_at = now() WHERE id = $1 RETURNING *",
+    )
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await?;
+
+    // Unused codes must not outlive the setting
+    sqlx::query("UPDATE email_otp_codes SET used_at = now() WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    Ok((StatusCode::OK, Json(UserResponse::from(user))))
+}
+
 // Get user profile
 pub async fn get_profile(
     State(pool): State<PgPool>,
@@ -194,6 +460,20 @@ pub async fn update_profile(
     auth_user: AuthUser,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<impl IntoResponse, AppError> {
+    // Passwords change through the dedicated endpoint, which checks the current one
+    if !payload["password"].is_null() {
+        return Err(AppError::BadRequest(
+            "Use POST /api/auth/password to change your password".to_string(),
+        ));
+    }
+
+    // Email changes need confirmation from the new address
+    if !payload["email"].is_null() {
+        return Err(AppError::BadRequest(
+            "Use POST /api/auth/email to change your email address".to_string(),
+        ));
+    }
+
     // Build the update query dynamically based on provided fields
     let mut query = "UPDATE users SET ".to_string();
     let mut binds = vec![];
@@ -203,33 +483,14 @@ pub async fn update_profile(
     if let Some(username) = payload["username"].as_str() {
         if i > 1 { query.push_str(", "); }
         query.push_str(&format!("username = ${}", i));
-        binds.push(username.to_string());
-        i += 1;
-    }
-    
-    // Email update
-    if let Some(email) = payload["email"].as_str() {
-        if i > 1 { query.push_str(", "); }
-        query.push_str(&format!("email = ${}", i));
-        binds.push(email.to_string());
-        i += 1;
-    }
-    
-    // Password update
-    if let Some(password) = payload["password"].as_str() {
-        let password_hash = hash_password(password)?;
-        if i > 1 { query.push_str(", "); }
-        query.push_str(&format!("password_hash = ${}", i));
-        binds.push(password_hash);
+        binds.push(normalize_username(username)?);
         i += 1;
     }
+
     
     // Update timestamp
-    let now = OffsetDateTime::now_utc();
     if i > 1 { query.push_str(", "); }
-    query.push_str(&format!("updated_at = ${}", i));
-    binds.push(now.to_string());
-    i += 1;
+    query.push_str("updated_at = now()");
     
     // Add WHERE clause
     query.push_str(&format!(" WHERE id = ${}", i));
@@ -246,7 +507,7 @@ pub async fn update_profile(
     db_query = db_query.bind(auth_user.user_id);
     
     // Execute query
-    db_query.execute(&pool).await?;
+    db_query.execute(&pool).await.map_err(unique_violation)?;
     
     // Get updated user
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
@@ -1,43 +1,304 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
-use sqlx::PgPool;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::{
     errors::AppError,
+    handlers::{
+        credential::{credential_responses, restore_credential},
+        revision::{category_snapshot, find_revision, record_revision, ItemChange},
+    },
     middleware::auth::AuthUser,
-    models::category::{Category, CategoryResponse, CreateCategory, UpdateCategory},
+    models::{
+        category::{
+            Category, CategoryDeletion, CategoryPathCreation, CategoryPathQuery, CategoryResponse,
+            CategoryWithCounts, CreateCategory, CreateCategoryPath, DeleteCategoryQuery, DeleteStrategy,
+            ReorderCategories, UpdateCategory, UNFILED_CATEGORY,
+        },
+        credential::{Credential, ItemType},
+        revision::CategorySnapshot,
+        trash::{TrashItem, TrashRestore, ITEM_CATEGORY, ITEM_CREDENTIAL},
+    },
 };
 
-// Get all categories for a user
+// Deepest nesting allowed; a top-level category is at depth 1
+pub const MAX_CATEGORY_DEPTH: usize = 10;
+
+// Longest icon name
+const MAX_ICON_LENGTH: usize = 64;
+
+// Colors are "#rrggbb"; icons are short names chosen by the client
+fn check_appearance(color: Option<&str>, icon: Option<&str>) -> Result<(), AppError> {
+    if let Some(color) = color {
+        let valid = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return Err(AppError::BadRequest("Color must be a hex value like #1e90ff".to_string()));
+        }
+    }
+
+    if let Some(icon) = icon {
+        if icon.trim().is_empty() || icon.chars().count() > MAX_ICON_LENGTH || icon.chars().any(char::is_control) {
+            return Err(AppError::BadRequest(format!(
+                "Icon must be between 1 and {} characters",
+                MAX_ICON_LENGTH
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Category names are stored trimmed and cannot contain "/", which separates names in paths
+fn check_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Category name cannot be empty".to_string()));
+    }
+    if name.contains('/') {
+        return Err(AppError::BadRequest("Category name cannot contain \"/\"".to_string()));
+    }
+
+    Ok(name.to_string())
+}
+
+// Map a duplicate sibling name to a readable conflict
+fn name_conflict(e: sqlx::Error) -> AppError {
+    let message = e.to_string();
+    if message.contains("categories_user_id_name_parent_id_key") || message.contains("idx_categories_user_id_root_name") {
+        AppError::Conflict("A category with this name already exists here".to_string())
+    } else {
+        AppError::Database(e)
+    }
+}
+
+// Serialise changes to one user's tree so concurrent moves cannot form a cycle
+async fn lock_tree(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// Ids from a category up to its top-level ancestor, starting with the category itself.
+// Empty when the category does not exist or belongs to someone else.
+async fn ancestor_ids(conn: &mut PgConnection, user_id: Uuid, category_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id, 1 AS depth FROM categories WHERE id = $1 AND user_id = $2
+            UNION ALL
+            SELECT c.id, c.parent_id, a.depth + 1
+            FROM categories c JOIN ancestors a ON c.id = a.parent_id
+            WHERE a.depth <= $3
+        )
+        SELECT id FROM ancestors ORDER BY depth
+        "#,
+    )
+    .bind(category_id)
+    .bind(user_id)
+    .bind(MAX_CATEGORY_DEPTH as i32)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(ids)
+}
+
+// Number of levels in the subtree rooted at a category, counting the category itself
+async fn subtree_height(conn: &mut PgConnection, category_id: Uuid) -> Result<usize, AppError> {
+    let height = sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 1 AS depth FROM categories WHERE id = $1
+            UNION ALL
+            SELECT c.id, s.depth + 1
+            FROM categories c JOIN subtree s ON c.parent_id = s.id
+            WHERE s.depth <= $2
+        )
+        SELECT MAX(depth) FROM subtree
+        "#,
+    )
+    .bind(category_id)
+    .bind(MAX_CATEGORY_DEPTH as i32)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(height.unwrap_or(1) as usize)
+}
+
+// Check that `parent_id` is one of the user's categories and can take `moving`
+// (an existing category and its subtree) or, when `None`, a new leaf
+async fn check_parent(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    parent_id: Uuid,
+    moving: Option<Uuid>,
+) -> Result<(), AppError> {
+    let ancestors = ancestor_ids(&mut *conn, user_id, parent_id).await?;
+    if ancestors.is_empty() {
+        return Err(AppError::NotFound("Parent category not found".to_string()));
+    }
+
+    let height = match moving {
+        Some(category_id) => {
+            if ancestors.contains(&category_id) {
+                return Err(AppError::BadRequest(
+                    "A category cannot be moved into itself or one of its subcategories".to_string(),
+                ));
+            }
+            subtree_height(&mut *conn, category_id).await?
+        }
+        None => 1,
+    };
+
+    if ancestors.len() + height > MAX_CATEGORY_DEPTH {
+        return Err(AppError::BadRequest(format!(
+            "Categories can be nested at most {} levels deep",
+            MAX_CATEGORY_DEPTH
+        )));
+    }
+
+    Ok(())
+}
+
+// Nest categories under their parents, keeping the order they were given in
+fn build_tree(categories: Vec<CategoryWithCounts>) -> Vec<CategoryResponse> {
+    fn attach(
+        parent_id: Option<Uuid>,
+        by_parent: &mut HashMap<Option<Uuid>, Vec<CategoryWithCounts>>,
+    ) -> Vec<CategoryResponse> {
+        by_parent
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| {
+                let id = row.category.id;
+                let mut response = CategoryResponse::from(row);
+                response.children = Some(attach(Some(id), by_parent));
+                response
+            })
+            .collect()
+    }
+
+    let mut by_parent: HashMap<Option<Uuid>, Vec<CategoryWithCounts>> = HashMap::new();
+    for row in categories {
+        by_parent.entry(row.category.parent_id).or_default().push(row);
+    }
+
+    attach(None, &mut by_parent)
+}
+
+// Load some of the user's categories with their credential counts, in sibling order
+async fn categories_with_counts(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    category_ids: &[Uuid],
+) -> Result<Vec<CategoryResponse>, AppError> {
+    // The closure pairs each category with itself and every category below it
+    let rows = sqlx::query_as::<_, CategoryWithCounts>(
+        r#"
+        WITH RECURSIVE closure AS (
+            SELECT id AS ancestor_id, id AS descendant_id, 1 AS depth
+            FROM categories WHERE id = ANY($1) AND user_id = $2
+            UNION ALL
+            SELECT cl.ancestor_id, c.id, cl.depth + 1
+            FROM closure cl JOIN categories c ON c.parent_id = cl.descendant_id
+            WHERE cl.depth <= $3
+        ),
+        counts AS (
+            SELECT category_id, COUNT(*) AS credential_count FROM credentials
+            WHERE category_id IN (SELECT descendant_id FROM closure)
+            GROUP BY category_id
+        )
+        SELECT c.*,
+            COALESCE(direct.credential_count, 0) AS credential_count,
+            COALESCE(subtree.credential_count, 0)::BIGINT AS subtree_credential_count
+        FROM categories c
+        LEFT JOIN counts direct ON direct.category_id = c.id
+        LEFT JOIN (
+            SELECT cl.ancestor_id, SUM(counts.credential_count) AS credential_count
+            FROM closure cl JOIN counts ON counts.category_id = cl.descendant_id
+            GROUP BY cl.ancestor_id
+        ) subtree ON subtree.ancestor_id = c.id
+        WHERE c.id = ANY($1) AND c.user_id = $2
+        ORDER BY c.position, c.name
+        "#,
+    )
+    .bind(category_ids)
+    .bind(user_id)
+    .bind(MAX_CATEGORY_DEPTH as i32)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows.into_iter().map(CategoryResponse::from).collect())
+}
+
+// Load one of the user's categories with its credential counts
+async fn category_with_counts(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    category_id: Uuid,
+) -> Result<CategoryResponse, AppError> {
+    categories_with_counts(conn, user_id, &[category_id])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::NotFound("Category not found".to_string()))
+}
+
+// Get all categories for a user as a tree
 pub async fn get_categories(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
 ) -> Result<impl IntoResponse, AppError> {
-    let categories = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories WHERE user_id = $1 ORDER BY name",
+    // Walk down from the top-level categories, then count credentials per
+    // category and per subtree in the same query
+    let rows = sqlx::query_as::<_, CategoryWithCounts>(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT c.*, 1 AS depth FROM categories c WHERE c.user_id = $1 AND c.parent_id IS NULL
+            UNION ALL
+            SELECT c.*, t.depth + 1 FROM categories c JOIN tree t ON c.parent_id = t.id
+            WHERE t.depth < $2
+        ),
+        closure AS (
+            SELECT id AS ancestor_id, id AS descendant_id FROM tree
+            UNION ALL
+            SELECT cl.ancestor_id, t.id FROM closure cl JOIN tree t ON t.parent_id = cl.descendant_id
+        ),
+        counts AS (
+            SELECT category_id, COUNT(*) AS credential_count FROM credentials
+            WHERE user_id = $1
+            GROUP BY category_id
+        )
+        SELECT t.*,
+            COALESCE(direct.credential_count, 0) AS credential_count,
+            COALESCE(subtree.credential_count, 0)::BIGINT AS subtree_credential_count
+        FROM tree t
+        LEFT JOIN counts direct ON direct.category_id = t.id
+        LEFT JOIN (
+            SELECT cl.ancestor_id, SUM(counts.credential_count) AS credential_count
+            FROM closure cl JOIN counts ON counts.category_id = cl.descendant_id
+            GROUP BY cl.ancestor_id
+        ) subtree ON subtree.ancestor_id = t.id
+        ORDER BY t.depth, t.position, t.name
+        "#,
     )
     .bind(auth_user.user_id)
+    .bind(MAX_CATEGORY_DEPTH as i32)
     .fetch_all(&pool)
     .await?;
 
-    // Convert to response format
-    let categories_response: Vec<CategoryResponse> = categories
-        .into_iter()
-        .map(|c| CategoryResponse {
-            id: c.id,
-            name: c.name,
-            parent_id: None, // No parent_id in current schema
-            created_at: c.created_at,
-            children: None,
-        })
-        .collect();
-
-    // Build the response
-    Ok((StatusCode::OK, Json(categories_response)))
+    Ok((StatusCode::OK, Json(build_tree(rows))))
 }
 
 // Get a single category by ID
@@ -46,79 +307,73 @@ pub async fn get_category(
     State(pool): State<PgPool>,
     Path(category_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Get the category
-    let category = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories WHERE id = $1 AND user_id = $2",
-    )
-    .bind(category_id)
-    .bind(auth_user.user_id)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or_else(|| AppError::NotFound("Category not found".to_string()))?;
-
-    // Convert to response format
-    let category_response = CategoryResponse {
-        id: category.id,
-        name: category.name,
-        parent_id: None, // No parent_id in current schema
-        created_at: category.created_at,
-        children: None,
-    };
+    let mut conn = pool.acquire().await?;
+    let category = category_with_counts(&mut conn, auth_user.user_id, category_id).await?;
 
-    Ok((StatusCode::OK, Json(category_response)))
+    Ok((StatusCode::OK, Json(category)))
 }
 
-// Create a new category
+// Create a new category, optionally inside another one
 pub async fn create_category(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
     Json(payload): Json<CreateCategory>,
 ) -> Result<impl IntoResponse, AppError> {
     // Validate inputs
-    if payload.name.trim().is_empty() {
-        return Err(AppError::BadRequest("Category name cannot be empty".to_string()));
+    let name = check_name(&payload.name)?;
+    check_appearance(payload.color.as_deref(), payload.icon.as_deref())?;
+
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
+    if let Some(parent_id) = payload.parent_id {
+        check_parent(&mut tx, auth_user.user_id, parent_id, None).await?;
     }
 
-    // Create the category
+    // Create the category after its siblings
     let category = sqlx::query_as::<_, Category>(
         r#"
-        INSERT INTO categories (user_id, name, description)
-        VALUES ($1, $2, $3)
+        INSERT INTO categories (user_id, name, description, parent_id, color, icon, collapsed, position)
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7,
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM categories WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $4)
+        )
         RETURNING *
         "#,
     )
     .bind(auth_user.user_id)
-    .bind(&payload.name)
+    .bind(&name)
     .bind(&payload.description)
-    .fetch_one(&pool)
-    .await?;
+    .bind(payload.parent_id)
+    .bind(&payload.color)
+    .bind(&payload.icon)
+    .bind(payload.collapsed)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(name_conflict)?;
 
-    // Convert to response format
-    let category_response = CategoryResponse {
-        id: category.id,
-        name: category.name,
-        parent_id: None, // No parent_id in current schema
-        created_at: category.created_at,
-        children: None,
-    };
+    tx.commit().await?;
 
-    Ok((StatusCode::CREATED, Json(category_response)))
+    Ok((StatusCode::CREATED, Json(CategoryResponse::from(category))))
 }
 
-// Update a category
+// Update a category; a new parent_id moves it together with its subtree
 pub async fn update_category(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
     Path(category_id): Path<Uuid>,
     Json(payload): Json<UpdateCategory>,
 ) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
     // Check if category exists and belongs to user
     let category_exists = sqlx::query_scalar::<_, bool>(
         "SELECT EXISTS(SELECT 1 FROM categories WHERE id = $1 AND user_id = $2)",
     )
     .bind(category_id)
     .bind(auth_user.user_id)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await?;
 
     if !category_exists {
@@ -126,43 +381,70 @@ pub async fn update_category(
     }
 
     // If name is provided, validate it
-    if let Some(ref name) = payload.name {
-        if name.trim().is_empty() {
-            return Err(AppError::BadRequest("Category name cannot be empty".to_string()));
-        }
-    }
+    let name = payload.name.as_deref().map(check_name).transpose()?;
+    check_appearance(
+        payload.color.as_ref().and_then(|color| color.as_deref()),
+        payload.icon.as_ref().and_then(|icon| icon.as_deref()),
+    )?;
+
+    let before = category_snapshot(&mut tx, category_id).await?;
 
     // Build update query dynamically based on provided fields
     let mut query = "UPDATE categories SET ".to_string();
-    let mut params = Vec::new();
+    let mut params: Vec<Option<String>> = Vec::new();
     let mut param_count = 1;
+    let mut assignments = 0;
 
     // Add name if provided
-    if let Some(name) = &payload.name {
+    if let Some(name) = name {
         query += &format!("name = ${}", param_count);
-        params.push(name.clone());
+        params.push(Some(name));
         param_count += 1;
+        assignments += 1;
     }
 
     // Add description if provided
     if let Some(description) = &payload.description {
-        if param_count > 1 {
+        if assignments > 0 {
             query += ", ";
         }
         query += &format!("description = ${}", param_count);
-        params.push(description.clone());
+        params.push(Some(description.clone()));
         param_count += 1;
+        assignments += 1;
+    }
+
+    // Add color and icon if provided (null clears them)
+    for (column, value) in [("color", &payload.color), ("icon", &payload.icon)] {
+        if let Some(value) = value {
+            if assignments > 0 {
+                query += ", ";
+            }
+            query += &format!("{} = ${}", column, param_count);
+            params.push(value.clone());
+            param_count += 1;
+            assignments += 1;
+        }
+    }
+
+    // Add collapsed if provided
+    if let Some(collapsed) = payload.collapsed {
+        if assignments > 0 {
+            query += ", ";
+        }
+        query += &format!("collapsed = {}", collapsed);
+        assignments += 1;
     }
 
     // Add updated_at
-    if param_count > 1 {
+    if assignments > 0 {
         query += ", ";
     }
     query += &format!("updated_at = now() WHERE id = ${} AND user_id = ${}", 
         param_count, param_count + 1);
 
     // Execute the query if any fields were provided
-    if param_count > 1 {
+    if assignments > 0 {
         let mut query_builder = sqlx::query(&query);
         
         // Add all the parameters
@@ -174,49 +456,998 @@ pub async fn update_category(
         query_builder = query_builder.bind(category_id).bind(auth_user.user_id);
         
         // Execute
-        query_builder.execute(&pool).await?;
+        query_builder.execute(&mut *tx).await.map_err(name_conflict)?;
     }
 
+    // Move the category if a parent was given (null moves it to the top level)
+    if let Some(parent_id) = payload.parent_id {
+        if let Some(parent_id) = parent_id {
+            check_parent(&mut tx, auth_user.user_id, parent_id, Some(category_id)).await?;
+        }
+
+        // It goes after its new siblings
+        sqlx::query(
+            r#"
+            UPDATE categories
+            SET parent_id = $1,
+                position = (
+                    SELECT COALESCE(MAX(position) + 1, 0) FROM categories
+                    WHERE user_id = $3 AND parent_id IS NOT DISTINCT FROM $1 AND id <> $2
+                ),
+                updated_at = now()
+            WHERE id = $2 AND user_id = $3 AND parent_id IS DISTINCT FROM $1
+            "#,
+        )
+        .bind(parent_id)
+        .bind(category_id)
+        .bind(auth_user.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(name_conflict)?;
+    }
+
+    let after = category_snapshot(&mut tx, category_id).await?;
+    let change = ItemChange { before: &before, after: &after, password_changed: false };
+    record_revision(&mut tx, &auth_user, ITEM_CATEGORY, category_id, change, None).await?;
+
     // Get the updated category
-    let category = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories WHERE id = $1 AND user_id = $2",
+    let category = category_with_counts(&mut tx, auth_user.user_id, category_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(category)))
+}
+
+// Put a category back the way it was at one of its revisions, moving it back under
+// its old parent if that changed. The rollback is itself recorded as a new revision.
+pub async fn rollback_category(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path((category_id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
+    let current_parent = sqlx::query_scalar::<_, Option<Uuid>>(
+        "SELECT parent_id FROM categories WHERE id = $1 AND user_id = $2",
     )
     .bind(category_id)
     .bind(auth_user.user_id)
-    .fetch_one(&pool)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Category not found".to_string()))?;
+
+    let revision = find_revision(&mut tx, auth_user.user_id, ITEM_CATEGORY, category_id, revision_id).await?;
+    let snapshot: CategorySnapshot = serde_json::from_value(revision.data)
+        .map_err(|e| AppError::Internal(format!("Failed to decode revision: {}", e)))?;
+
+    // Revisions from before names were checked may hold names that are no longer allowed
+    let name = check_name(&snapshot.name)?;
+
+    // The old parent must still exist and still be able to take this subtree
+    if snapshot.parent_id != current_parent {
+        if let Some(parent_id) = snapshot.parent_id {
+            match check_parent(&mut tx, auth_user.user_id, parent_id, Some(category_id)).await {
+                Err(AppError::NotFound(_)) => {
+                    return Err(AppError::Conflict("The parent of this revision no longer exists".to_string()));
+                }
+                result => result?,
+            }
+        }
+    }
+
+    let before = category_snapshot(&mut tx, category_id).await?;
+
+    // A category that moves goes after its new siblings
+    sqlx::query(
+        r#"
+        UPDATE categories
+        SET name = $1, description = $2, color = $3, icon = $4, collapsed = $5,
+            position = CASE
+                WHEN parent_id IS DISTINCT FROM $6 THEN (
+                    SELECT COALESCE(MAX(position) + 1, 0) FROM categories
+                    WHERE user_id = $8 AND parent_id IS NOT DISTINCT FROM $6 AND id <> $7
+                )
+                ELSE position
+            END,
+            parent_id = $6,
+            updated_at = now()
+        WHERE id = $7 AND user_id = $8
+        "#,
+    )
+    .bind(&name)
+    .bind(&snapshot.description)
+    .bind(&snapshot.color)
+    .bind(&snapshot.icon)
+    .bind(snapshot.collapsed)
+    .bind(snapshot.parent_id)
+    .bind(category_id)
+    .bind(auth_user.user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(name_conflict)?;
+
+    let after = category_snapshot(&mut tx, category_id).await?;
+    let change = ItemChange { before: &before, after: &after, password_changed: false };
+    record_revision(&mut tx, &auth_user, ITEM_CATEGORY, category_id, change, Some(revision.id)).await?;
+
+    let category = category_with_counts(&mut tx, auth_user.user_id, category_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(category)))
+}
+
+// Set the order of the subcategories of one parent (or of the top level)
+pub async fn reorder_categories(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Json(payload): Json<ReorderCategories>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
+    if let Some(parent_id) = payload.parent_id {
+        if ancestor_ids(&mut tx, auth_user.user_id, parent_id).await?.is_empty() {
+            return Err(AppError::NotFound("Parent category not found".to_string()));
+        }
+    }
+
+    let mut siblings = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM categories WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $2",
+    )
+    .bind(auth_user.user_id)
+    .bind(payload.parent_id)
+    .fetch_all(&mut *tx)
     .await?;
 
-    // Convert to response format
-    let category_response = CategoryResponse {
-        id: category.id,
-        name: category.name,
-        parent_id: None, // No parent_id in current schema
-        created_at: category.created_at,
-        children: None,
-    };
+    // The new order must be a permutation of the current siblings
+    let mut requested = payload.order.clone();
+    siblings.sort();
+    requested.sort();
+    if siblings != requested {
+        return Err(AppError::BadRequest(
+            "order must list every subcategory of the parent exactly once".to_string(),
+        ));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE categories c SET position = o.position - 1, updated_at = now()
+        FROM UNNEST($1::uuid[]) WITH ORDINALITY AS o(id, position)
+        WHERE c.id = o.id AND c.user_id = $2
+        "#,
+    )
+    .bind(&payload.order)
+    .bind(auth_user.user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let categories = categories_with_counts(&mut tx, auth_user.user_id, &payload.order).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(categories)))
+}
+
+// Ids of a category and every category below it
+async fn subtree_ids(conn: &mut PgConnection, category_id: Uuid) -> Result<Vec<Uuid>, AppError> {
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 1 AS depth FROM categories WHERE id = $1
+            UNION ALL
+            SELECT c.id, s.depth + 1
+            FROM categories c JOIN subtree s ON c.parent_id = s.id
+            WHERE s.depth <= $2
+        )
+        SELECT id FROM subtree ORDER BY depth
+        "#,
+    )
+    .bind(category_id)
+    .bind(MAX_CATEGORY_DEPTH as i32)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(ids)
+}
+
+// Find the user's top-level "Unfiled" category, creating it if needed
+pub async fn unfiled_category(conn: &mut PgConnection, user_id: Uuid) -> Result<Uuid, AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO categories (user_id, name, position)
+        VALUES ($1, $2, (SELECT COALESCE(MAX(position) + 1, 0) FROM categories WHERE user_id = $1 AND parent_id IS NULL))
+        ON CONFLICT (user_id, name) WHERE parent_id IS NULL DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(UNFILED_CATEGORY)
+    .execute(&mut *conn)
+    .await?;
+
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM categories WHERE user_id = $1 AND name = $2 AND parent_id IS NULL",
+    )
+    .bind(user_id)
+    .bind(UNFILED_CATEGORY)
+    .fetch_one(conn)
+    .await?;
 
-    Ok((StatusCode::OK, Json(category_response)))
+    Ok(id)
 }
 
-// Delete a category
+// Delete a category, handling its contents as the strategy says (reject by default)
 pub async fn delete_category(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
     Path(category_id): Path<Uuid>,
+    Query(query): Query<DeleteCategoryQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Delete the category (but only if it belongs to the authenticated user)
-    let result = sqlx::query(
-        "DELETE FROM categories WHERE id = $1 AND user_id = $2",
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
+    // Only the user's own categories
+    let category = sqlx::query_as::<_, Category>(
+        "SELECT * FROM categories WHERE id = $1 AND user_id = $2",
     )
     .bind(category_id)
     .bind(auth_user.user_id)
-    .execute(&pool)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Category not found".to_string()))?;
+
+    let child_ids = sqlx::query_scalar::<_, Uuid>("SELECT id FROM categories WHERE parent_id = $1 ORDER BY name")
+        .bind(category_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let credential_ids = sqlx::query_scalar::<_, Uuid>("SELECT id FROM credentials WHERE category_id = $1 ORDER BY name")
+        .bind(category_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut report = CategoryDeletion {
+        strategy: query.strategy,
+        deleted_categories: vec![category_id],
+        moved_to: None,
+        moved_categories: Vec::new(),
+        moved_credentials: Vec::new(),
+        trashed_credentials: Vec::new(),
+    };
+
+    // Where the direct contents go, if anywhere
+    let destination = match query.strategy {
+        DeleteStrategy::Reject => {
+            if !child_ids.is_empty() || !credential_ids.is_empty() {
+                return Err(AppError::Conflict(
+                    "Category is not empty; choose a deletion strategy for its contents".to_string(),
+                ));
+            }
+            None
+        }
+        DeleteStrategy::MoveToParent => {
+            // Subcategories can go to the top level, but credentials need a category
+            if category.parent_id.is_none() && !credential_ids.is_empty() {
+                return Err(AppError::BadRequest(
+                    "A top-level category has no parent to take its credentials".to_string(),
+                ));
+            }
+            Some(category.parent_id)
+        }
+        DeleteStrategy::MoveToUnfiled => {
+            if category.parent_id.is_none() && category.name == UNFILED_CATEGORY {
+                return Err(AppError::BadRequest(
+                    "The Unfiled category cannot be emptied into itself".to_string(),
+                ));
+            }
+            Some(Some(unfiled_category(&mut tx, auth_user.user_id).await?))
+        }
+        DeleteStrategy::Trash => None,
+    };
+
+    if let Some(destination) = destination {
+        // Moving up a level never makes the tree deeper. The moved categories keep
+        // their order and go after the destination's own subcategories.
+        sqlx::query(
+            r#"
+            UPDATE categories
+            SET parent_id = $1,
+                position = position + (
+                    SELECT COALESCE(MAX(position) + 1, 0) FROM categories
+                    WHERE user_id = $3 AND parent_id IS NOT DISTINCT FROM $1
+                ),
+                updated_at = now()
+            WHERE parent_id = $2
+            "#,
+        )
+        .bind(destination)
+        .bind(category_id)
+        .bind(auth_user.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(name_conflict)?;
+
+        if let Some(destination) = destination {
+            sqlx::query("UPDATE credentials SET category_id = $1, updated_at = now() WHERE category_id = $2")
+                .bind(destination)
+                .bind(category_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        report.moved_to = destination;
+        report.moved_categories = child_ids;
+        report.moved_credentials = credential_ids;
+    } else if query.strategy == DeleteStrategy::Trash {
+        let subtree = subtree_ids(&mut tx, category_id).await?;
+
+        // Keep a copy of every row before it goes; credentials carry their custom fields
+        // and password history
+        sqlx::query(
+            r#"
+            INSERT INTO trash_items (user_id, item_type, item_id, data)
+            SELECT user_id, $2, id, to_jsonb(c) FROM categories c WHERE id = ANY($1)
+            "#,
+        )
+        .bind(&subtree)
+        .bind(ITEM_CATEGORY)
+        .execute(&mut *tx)
+        .await?;
+
+        report.trashed_credentials = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO trash_items (user_id, item_type, item_id, data)
+            SELECT user_id, $2, id, to_jsonb(c) || jsonb_build_object(
+                'fields', COALESCE(
+                    (SELECT jsonb_agg(to_jsonb(f) ORDER BY f.position) FROM credential_fields f WHERE f.credential_id = c.id),
+                    '[]'::jsonb
+                ),
+                'password_history', COALESCE(
+                    (SELECT jsonb_agg(to_jsonb(h) ORDER BY h.replaced_at DESC) FROM password_history h WHERE h.credential_id = c.id),
+                    '[]'::jsonb
+                )
+            )
+            FROM credentials c WHERE category_id = ANY($1)
+            RETURNING item_id
+            "#,
+        )
+        .bind(&subtree)
+        .bind(ITEM_CREDENTIAL)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM credentials WHERE category_id = ANY($1)")
+            .bind(&subtree)
+            .execute(&mut *tx)
+            .await?;
+
+        report.deleted_categories = subtree;
+    }
+
+    // Subcategories still attached at this point are removed by the parent_id cascade
+    sqlx::query("DELETE FROM categories WHERE id = $1 AND user_id = $2")
+        .bind(category_id)
+        .bind(auth_user.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            // Credentials must always have a category, so they block the delete
+            if e.to_string().contains("credentials_category_id_fkey") {
+                AppError::Conflict("Category still contains credentials".to_string())
+            } else {
+                AppError::Database(e)
+            }
+        })?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(report)))
+}
+
+// List deleted categories and credentials, newest first
+pub async fn get_trash(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let items = sqlx::query_as::<_, TrashItem>(
+        r#"
+        SELECT id, item_type, item_id, data->>'name' AS name, deleted_at
+        FROM trash_items WHERE user_id = $1
+        ORDER BY deleted_at DESC, item_type
+        "#,
+    )
+    .bind(auth_user.user_id)
+    .fetch_all(&pool)
     .await?;
 
-    // Check if anything was deleted
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound("Category not found".to_string()));
+    Ok((StatusCode::OK, Json(items)))
+}
+
+// Recreate a trashed category. It goes back under its old parent when that still
+// exists, otherwise to the top level, after the categories already there.
+async fn restore_category(conn: &mut PgConnection, user_id: Uuid, data: &Value) -> Result<Uuid, AppError> {
+    let parent_id = match data["parent_id"].as_str().and_then(|id| id.parse::<Uuid>().ok()) {
+        Some(parent_id) if !ancestor_ids(&mut *conn, user_id, parent_id).await?.is_empty() => Some(parent_id),
+        _ => None,
+    };
+
+    let position = sqlx::query_scalar::<_, i32>(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM categories WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $2",
+    )
+    .bind(user_id)
+    .bind(parent_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let mut row = data.clone();
+    row["parent_id"] = parent_id.map_or(Value::Null, |id| Value::String(id.to_string()));
+    row["position"] = Value::from(position);
+
+    insert_trashed_category(conn, &row).await
+}
+
+async fn insert_trashed_category(conn: &mut PgConnection, row: &Value) -> Result<Uuid, AppError> {
+    sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO categories SELECT * FROM jsonb_populate_record(NULL::categories, $1) RETURNING id",
+    )
+    .bind(row)
+    .fetch_one(conn)
+    .await
+    .map_err(name_conflict)
+}
+
+// Put a trashed item back. A category brings back everything that was trashed
+// beneath it in the same deletion; a credential brings back its custom fields and
+// password history, and goes to Unfiled if its category no longer exists.
+pub async fn restore_trash_item(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(trash_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = auth_user.user_id;
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, user_id).await?;
+
+    let (item_type, data, deleted_at) = sqlx::query_as::<_, (String, Value, OffsetDateTime)>(
+        "DELETE FROM trash_items WHERE id = $1 AND user_id = $2 RETURNING item_type, data, deleted_at",
+    )
+    .bind(trash_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Trash item not found".to_string()))?;
+
+    let mut report = TrashRestore {
+        restored_categories: Vec::new(),
+        restored_credentials: Vec::new(),
+    };
+
+    if item_type == ITEM_CATEGORY {
+        let root = restore_category(&mut tx, user_id, &data).await?;
+        report.restored_categories.push(root);
+
+        // Rows trashed by one deletion share its timestamp; bring back the subtree level by level
+        let mut level = vec![root];
+        while !level.is_empty() {
+            let children = sqlx::query_scalar::<_, Value>(
+                r#"
+                DELETE FROM trash_items
+                WHERE user_id = $1 AND item_type = $2 AND deleted_at = $3 AND (data->>'parent_id')::uuid = ANY($4)
+                RETURNING data
+                "#,
+            )
+            .bind(user_id)
+            .bind(ITEM_CATEGORY)
+            .bind(deleted_at)
+            .bind(&level)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            level.clear();
+            for child in &children {
+                level.push(insert_trashed_category(&mut tx, child).await?);
+            }
+            report.restored_categories.extend(&level);
+        }
+
+        let ancestors = ancestor_ids(&mut tx, user_id, root).await?;
+        if ancestors.len() - 1 + subtree_height(&mut tx, root).await? > MAX_CATEGORY_DEPTH {
+            return Err(AppError::BadRequest(format!(
+                "Restoring this category would nest categories more than {} levels deep",
+                MAX_CATEGORY_DEPTH
+            )));
+        }
+
+        let credentials = sqlx::query_scalar::<_, Value>(
+            r#"
+            DELETE FROM trash_items
+            WHERE user_id = $1 AND item_type = $2 AND deleted_at = $3 AND (data->>'category_id')::uuid = ANY($4)
+            RETURNING data
+            "#,
+        )
+        .bind(user_id)
+        .bind(ITEM_CREDENTIAL)
+        .bind(deleted_at)
+        .bind(&report.restored_categories)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for credential in &credentials {
+            let category_id = credential["category_id"]
+                .as_str()
+                .and_then(|id| id.parse::<Uuid>().ok())
+                .ok_or_else(|| AppError::Internal("Trashed credential has no category".to_string()))?;
+            report.restored_credentials.push(restore_credential(&mut tx, credential, category_id).await?);
+        }
+    } else {
+        let category_id = match data["category_id"].as_str().and_then(|id| id.parse::<Uuid>().ok()) {
+            Some(category_id) if !ancestor_ids(&mut tx, user_id, category_id).await?.is_empty() => category_id,
+            _ => unfiled_category(&mut tx, user_id).await?,
+        };
+        report.restored_credentials.push(restore_credential(&mut tx, &data, category_id).await?);
+    }
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(report)))
+}
+
+// Split "Infra/AWS/prod" into its names. Leading and trailing slashes are ignored;
+// empty names in the middle are not.
+fn split_path(path: &str) -> Result<Vec<String>, AppError> {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Err(AppError::BadRequest("Category path cannot be empty".to_string()));
+    }
+
+    let names: Vec<String> = trimmed.split('/').map(|name| name.trim().to_string()).collect();
+    if names.iter().any(|name| name.is_empty()) {
+        return Err(AppError::BadRequest("Category path contains an empty name".to_string()));
+    }
+
+    if names.len() > MAX_CATEGORY_DEPTH {
+        return Err(AppError::BadRequest(format!(
+            "Categories can be nested at most {} levels deep",
+            MAX_CATEGORY_DEPTH
+        )));
+    }
+
+    Ok(names)
+}
+
+// Follow a path down from the top level and return the ids matched so far.
+// The path exists when every name matched.
+async fn resolve_path(conn: &mut PgConnection, user_id: Uuid, names: &[String]) -> Result<Vec<Uuid>, AppError> {
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        WITH RECURSIVE walk AS (
+            SELECT id, 1 AS depth FROM categories
+            WHERE user_id = $1 AND parent_id IS NULL AND name = $2[1]
+            UNION ALL
+            SELECT c.id, w.depth + 1
+            FROM categories c JOIN walk w ON c.parent_id = w.id
+            WHERE c.name = $2[w.depth + 1]
+        )
+        SELECT id FROM walk ORDER BY depth
+        "#,
+    )
+    .bind(user_id)
+    .bind(names)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(ids)
+}
+
+// Resolve a path to the category at its end
+async fn category_at_path(conn: &mut PgConnection, user_id: Uuid, path: &str) -> Result<Category, AppError> {
+    let names = split_path(path)?;
+    let ids = resolve_path(&mut *conn, user_id, &names).await?;
+
+    let category_id = match ids.get(names.len() - 1) {
+        Some(id) => *id,
+        None => return Err(AppError::NotFound("Category not found".to_string())),
+    };
+
+    let category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1")
+        .bind(category_id)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(category)
+}
+
+// Get the category at a path
+pub async fn get_category_by_path(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Query(query): Query<CategoryPathQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let category = category_at_path(&mut conn, auth_user.user_id, &query.path).await?;
+    let category = category_with_counts(&mut conn, auth_user.user_id, category.id).await?;
+
+    Ok((StatusCode::OK, Json(category)))
+}
+
+// Get the credentials in the category at a path, and in its subcategories if recursive,
+// optionally of one type
+pub async fn get_credentials_by_path(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Query(query): Query<CategoryPathQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let category = category_at_path(&mut conn, auth_user.user_id, &query.path).await?;
+
+    let category_ids = if query.recursive {
+        subtree_ids(&mut conn, category.id).await?
+    } else {
+        vec![category.id]
+    };
+
+    let credentials = sqlx::query_as::<_, Credential>(
+        r#"
+        SELECT * FROM credentials
+        WHERE category_id = ANY($1) AND user_id = $2 AND ($3::text IS NULL OR item_type = $3)
+        ORDER BY name
+        "#,
+    )
+    .bind(&category_ids)
+    .bind(auth_user.user_id)
+    .bind(query.item_type.map(ItemType::as_str))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Convert to response format (without passwords)
+    let credentials_response = credential_responses(&mut conn, credentials).await?;
+
+    Ok((StatusCode::OK, Json(credentials_response)))
+}
+
+// Create every missing category along a path, like `mkdir -p`
+pub async fn create_category_path(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateCategoryPath>,
+) -> Result<impl IntoResponse, AppError> {
+    let names = split_path(&payload.path)?;
+
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx, auth_user.user_id).await?;
+
+    let existing = resolve_path(&mut tx, auth_user.user_id, &names).await?;
+    let mut parent_id = existing.last().copied();
+    let mut created = Vec::new();
+
+    for name in &names[existing.len()..] {
+        let category = sqlx::query_as::<_, Category>(
+            r#"
+            INSERT INTO categories (user_id, name, parent_id, position)
+            VALUES (
+                $1, $2, $3,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM categories WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $3)
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(auth_user.user_id)
+        .bind(name)
+        .bind(parent_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(name_conflict)?;
+
+        parent_id = Some(category.id);
+        created.push(CategoryResponse::from(category));
     }
 
-    Ok(StatusCode::NO_CONTENT)
-} 
\ No newline at end of file
+    let category = match (created.last(), parent_id) {
+        (Some(category), _) => category.clone(),
+        (None, Some(category_id)) => category_with_counts(&mut tx, auth_user.user_id, category_id).await?,
+        (None, None) => return Err(AppError::NotFound("Category not found".to_string())),
+    };
+
+    tx.commit().await?;
+
+    // 201 only when something was created
+    let status = if created.is_empty() { StatusCode::OK } else { StatusCode::CREATED };
+    Ok((status, Json(CategoryPathCreation { category, created })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::test_pool,
+        handlers::{response_status as status, test_credential, test_user},
+    };
+    use serde_json::json;
+
+    // Add a category without going through the checks under test
+    async fn insert_category(pool: &PgPool, user_id: Uuid, name: &str, parent_id: Option<Uuid>) -> Uuid {
+        sqlx::query_scalar("INSERT INTO categories (user_id, name, parent_id) VALUES ($1, $2, $3) RETURNING id")
+            .bind(user_id)
+            .bind(name)
+            .bind(parent_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    // A chain of nested categories, top level first
+    async fn insert_chain(pool: &PgPool, user_id: Uuid, depth: usize) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = Vec::new();
+        for level in 1..=depth {
+            ids.push(insert_category(pool, user_id, &format!("Level {}", level), ids.last().copied()).await);
+        }
+        ids
+    }
+
+    async fn parent_of(pool: &PgPool, category_id: Uuid) -> Option<Uuid> {
+        sqlx::query_scalar("SELECT parent_id FROM categories WHERE id = $1")
+            .bind(category_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    // The category of a credential, or None once it is gone
+    async fn category_of(pool: &PgPool, credential_id: Uuid) -> Option<Uuid> {
+        sqlx::query_scalar("SELECT category_id FROM credentials WHERE id = $1")
+            .bind(credential_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn category_exists(pool: &PgPool, category_id: Uuid) -> bool {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM categories WHERE id = $1)")
+            .bind(category_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn unfiled_id(pool: &PgPool, user_id: Uuid) -> Uuid {
+        sqlx::query_scalar("SELECT id FROM categories WHERE user_id = $1 AND parent_id IS NULL AND name = $2")
+            .bind(user_id)
+            .bind(UNFILED_CATEGORY)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn delete(pool: &PgPool, user: &AuthUser, category_id: Uuid, strategy: DeleteStrategy) -> StatusCode {
+        let query = DeleteCategoryQuery { strategy };
+        status(delete_category(user.clone(), State(pool.clone()), Path(category_id), Query(query)).await)
+    }
+
+    async fn trash_item(pool: &PgPool, item_id: Uuid) -> Uuid {
+        sqlx::query_scalar("SELECT id FROM trash_items WHERE item_id = $1")
+            .bind(item_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_category_cannot_move_into_itself_or_its_subtree() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let ids = insert_chain(&pool, user.user_id, 3).await;
+
+        for target in [ids[0], ids[2]] {
+            let payload = serde_json::from_value(json!({ "parent_id": target })).unwrap();
+            let result = update_category(user.clone(), State(pool.clone()), Path(ids[0]), Json(payload)).await;
+            assert_eq!(status(result), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(parent_of(&pool, ids[0]).await, None);
+
+        // Moving a subtree elsewhere in the tree is fine
+        let payload = serde_json::from_value(json!({ "parent_id": null })).unwrap();
+        let result = update_category(user.clone(), State(pool.clone()), Path(ids[2]), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::OK);
+        assert_eq!(parent_of(&pool, ids[2]).await, None);
+    }
+
+    #[tokio::test]
+    async fn categories_cannot_nest_beyond_the_depth_limit() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let chain = insert_chain(&pool, user.user_id, MAX_CATEGORY_DEPTH).await;
+
+        let payload = serde_json::from_value(json!({ "name": "Too deep", "parent_id": chain[MAX_CATEGORY_DEPTH - 1] })).unwrap();
+        let result = create_category(user.clone(), State(pool.clone()), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::BAD_REQUEST);
+
+        let payload = serde_json::from_value(json!({ "name": "Deepest", "parent_id": chain[MAX_CATEGORY_DEPTH - 2] })).unwrap();
+        let result = create_category(user.clone(), State(pool.clone()), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::CREATED);
+
+        // A two-level subtree fits at the top but not under the second-deepest level
+        let top = insert_category(&pool, user.user_id, "Top", None).await;
+        insert_category(&pool, user.user_id, "Child", Some(top)).await;
+        let payload = serde_json::from_value(json!({ "parent_id": chain[MAX_CATEGORY_DEPTH - 2] })).unwrap();
+        let result = update_category(user.clone(), State(pool.clone()), Path(top), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::BAD_REQUEST);
+        assert_eq!(parent_of(&pool, top).await, None);
+    }
+
+    #[tokio::test]
+    async fn reject_strategy_only_deletes_empty_categories() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let parent = insert_category(&pool, user.user_id, "Parent", None).await;
+        let child = insert_category(&pool, user.user_id, "Child", Some(parent)).await;
+        let credential = test_credential(&pool, user.user_id, "Login", child).await;
+
+        assert_eq!(delete(&pool, &user, parent, DeleteStrategy::Reject).await, StatusCode::CONFLICT);
+        assert_eq!(delete(&pool, &user, child, DeleteStrategy::Reject).await, StatusCode::CONFLICT);
+        assert!(category_exists(&pool, child).await);
+
+        sqlx::query("DELETE FROM credentials WHERE id = $1").bind(credential).execute(&pool).await.unwrap();
+        assert_eq!(delete(&pool, &user, child, DeleteStrategy::Reject).await, StatusCode::OK);
+        assert!(!category_exists(&pool, child).await);
+    }
+
+    #[tokio::test]
+    async fn move_strategies_keep_the_contents() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let top = insert_category(&pool, user.user_id, "Top", None).await;
+        let middle = insert_category(&pool, user.user_id, "Middle", Some(top)).await;
+        let child = insert_category(&pool, user.user_id, "Child", Some(middle)).await;
+        let credential = test_credential(&pool, user.user_id, "Login", middle).await;
+
+        assert_eq!(delete(&pool, &user, middle, DeleteStrategy::MoveToParent).await, StatusCode::OK);
+        assert!(!category_exists(&pool, middle).await);
+        assert_eq!(parent_of(&pool, child).await, Some(top));
+        assert_eq!(category_of(&pool, credential).await, Some(top));
+
+        // A top-level category has no parent to hold credentials
+        assert_eq!(delete(&pool, &user, top, DeleteStrategy::MoveToParent).await, StatusCode::BAD_REQUEST);
+
+        assert_eq!(delete(&pool, &user, top, DeleteStrategy::MoveToUnfiled).await, StatusCode::OK);
+        let unfiled = unfiled_id(&pool, user.user_id).await;
+        assert_eq!(parent_of(&pool, child).await, Some(unfiled));
+        assert_eq!(category_of(&pool, credential).await, Some(unfiled));
+        assert_eq!(delete(&pool, &user, unfiled, DeleteStrategy::MoveToUnfiled).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn trashed_subtrees_can_be_restored() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let top = insert_category(&pool, user.user_id, "Top", None).await;
+        let child = insert_category(&pool, user.user_id, "Child", Some(top)).await;
+        let credential = test_credential(&pool, user.user_id, "Login", child).await;
+        sqlx::query("INSERT INTO password_history (credential_id, password) SELECT id, password FROM credentials WHERE id = $1")
+            .bind(credential)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(delete(&pool, &user, top, DeleteStrategy::Trash).await, StatusCode::OK);
+        assert!(!category_exists(&pool, child).await);
+        assert_eq!(category_of(&pool, credential).await, None);
+
+        let item = trash_item(&pool, top).await;
+        assert_eq!(status(restore_trash_item(user.clone(), State(pool.clone()), Path(item)).await), StatusCode::OK);
+        assert_eq!(parent_of(&pool, child).await, Some(top));
+        assert_eq!(category_of(&pool, credential).await, Some(child));
+
+        let history = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM password_history WHERE credential_id = $1")
+            .bind(credential)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(history, 1);
+
+        // The whole deletion came back at once
+        let left = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM trash_items WHERE user_id = $1")
+            .bind(user.user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+        assert_eq!(status(restore_trash_item(user.clone(), State(pool.clone()), Path(item)).await), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn a_restored_credential_without_its_category_goes_to_unfiled() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let top = insert_category(&pool, user.user_id, "Top", None).await;
+        let credential = test_credential(&pool, user.user_id, "Login", top).await;
+
+        assert_eq!(delete(&pool, &user, top, DeleteStrategy::Trash).await, StatusCode::OK);
+
+        let item = trash_item(&pool, credential).await;
+        assert_eq!(status(restore_trash_item(user.clone(), State(pool.clone()), Path(item)).await), StatusCode::OK);
+        let unfiled = unfiled_id(&pool, user.user_id).await;
+        assert_eq!(category_of(&pool, credential).await, Some(unfiled));
+        assert!(!category_exists(&pool, top).await);
+    }
+
+    async fn create_path(pool: &PgPool, user: &AuthUser, path: &str) -> StatusCode {
+        let payload = CreateCategoryPath { path: path.to_string() };
+        status(create_category_path(user.clone(), State(pool.clone()), Json(payload)).await)
+    }
+
+    async fn get_path(pool: &PgPool, user: &AuthUser, path: &str) -> StatusCode {
+        let query = CategoryPathQuery { path: path.to_string(), recursive: false, item_type: None };
+        status(get_category_by_path(user.clone(), State(pool.clone()), Query(query)).await)
+    }
+
+    async fn category_count(pool: &PgPool, user_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn creating_a_path_only_adds_the_missing_categories() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+
+        assert_eq!(create_path(&pool, &user, "Infra/AWS").await, StatusCode::CREATED);
+        assert_eq!(category_count(&pool, user.user_id).await, 2);
+
+        assert_eq!(create_path(&pool, &user, "/Infra/ AWS /prod/").await, StatusCode::CREATED);
+        assert_eq!(category_count(&pool, user.user_id).await, 3);
+
+        assert_eq!(create_path(&pool, &user, "Infra/AWS/prod").await, StatusCode::OK);
+        assert_eq!(category_count(&pool, user.user_id).await, 3);
+
+        assert_eq!(create_path(&pool, &user, "Infra//prod").await, StatusCode::BAD_REQUEST);
+        let too_deep = ["Level"; MAX_CATEGORY_DEPTH + 1].join("/");
+        assert_eq!(create_path(&pool, &user, &too_deep).await, StatusCode::BAD_REQUEST);
+        assert_eq!(category_count(&pool, user.user_id).await, 3);
+    }
+
+    #[tokio::test]
+    async fn paths_resolve_by_exact_name() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let infra = insert_category(&pool, user.user_id, "Infra", None).await;
+        insert_category(&pool, user.user_id, "AWS", Some(infra)).await;
+
+        assert_eq!(get_path(&pool, &user, "Infra/AWS").await, StatusCode::OK);
+        assert_eq!(get_path(&pool, &user, "/Infra/AWS/").await, StatusCode::OK);
+        assert_eq!(get_path(&pool, &user, "infra/aws").await, StatusCode::NOT_FOUND);
+        assert_eq!(get_path(&pool, &user, "AWS").await, StatusCode::NOT_FOUND);
+        assert_eq!(get_path(&pool, &user, "Infra/AWS/prod").await, StatusCode::NOT_FOUND);
+        assert_eq!(get_path(&pool, &user, "/").await, StatusCode::BAD_REQUEST);
+
+        // Another user's tree is not visible
+        let other = test_user(&pool).await;
+        assert_eq!(get_path(&pool, &other, "Infra/AWS").await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn names_are_trimmed_and_cannot_contain_slashes() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+
+        let payload = serde_json::from_value(json!({ "name": "Infra/AWS" })).unwrap();
+        assert_eq!(status(create_category(user.clone(), State(pool.clone()), Json(payload)).await), StatusCode::BAD_REQUEST);
+
+        let payload = serde_json::from_value(json!({ "name": "  Infra  " })).unwrap();
+        assert_eq!(status(create_category(user.clone(), State(pool.clone()), Json(payload)).await), StatusCode::CREATED);
+        assert_eq!(get_path(&pool, &user, "Infra").await, StatusCode::OK);
+
+        let infra = sqlx::query_scalar::<_, Uuid>("SELECT id FROM categories WHERE user_id = $1")
+            .bind(user.user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let payload = serde_json::from_value(json!({ "name": "a/b" })).unwrap();
+        let result = update_category(user.clone(), State(pool.clone()), Path(infra), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::BAD_REQUEST);
+        assert_eq!(get_path(&pool, &user, "Infra").await, StatusCode::OK);
+    }
+}
@@ -1,59 +1,305 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
-use sqlx::PgPool;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::{collections::HashMap, env};
 use uuid::Uuid;
 
 use crate::{
     crypto::{encrypt_password, decrypt_password},
     errors::AppError,
+    handlers::revision::{credential_snapshot, find_revision, record_revision, ItemChange},
     middleware::auth::AuthUser,
-    models::credential::{
-        Credential, CreateCredential, CredentialResponse, 
-        CredentialWithPassword, UpdateCredential
+    models::{
+        credential::{
+            Credential, CredentialField, CreateCredential, CredentialFilter, CredentialResponse,
+            CredentialWithPassword, ItemType, PasswordHistoryEntry, PasswordHistoryResponse, UpdateCredential
+        },
+        revision::CredentialSnapshot,
+        trash::ITEM_CREDENTIAL,
+    },
+    vault::{
+        fields::{field_response, parse_fields, FieldRow},
+        item_metadata, open_item_data, parse_item_data, seal_item_data,
     },
 };
 
-// Get all credentials for a user
+// Previous passwords kept per login when PASSWORD_HISTORY_DEPTH is not set
+const DEFAULT_PASSWORD_HISTORY_DEPTH: i64 = 10;
+
+// How many previous passwords each login keeps; 0 turns the history off
+pub fn password_history_depth() -> i64 {
+    env::var("PASSWORD_HISTORY_DEPTH")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|depth| *depth >= 0)
+        .unwrap_or(DEFAULT_PASSWORD_HISTORY_DEPTH)
+}
+
+// Credentials may only be filed in the user's own categories
+async fn check_category(pool: &PgPool, user_id: Uuid, category_id: Uuid) -> Result<(), AppError> {
+    let category_exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM categories WHERE id = $1 AND user_id = $2)",
+    )
+    .bind(category_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    if !category_exists {
+        return Err(AppError::NotFound("Category not found".to_string()));
+    }
+
+    Ok(())
+}
+
+// Load the custom fields of some credentials, in order, keyed by credential
+async fn credential_fields(
+    conn: &mut PgConnection,
+    credential_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<CredentialField>>, AppError> {
+    let fields = sqlx::query_as::<_, CredentialField>(
+        "SELECT * FROM credential_fields WHERE credential_id = ANY($1) ORDER BY credential_id, position",
+    )
+    .bind(credential_ids)
+    .fetch_all(conn)
+    .await?;
+
+    let mut by_credential: HashMap<Uuid, Vec<CredentialField>> = HashMap::new();
+    for field in fields {
+        by_credential.entry(field.credential_id).or_default().push(field);
+    }
+
+    Ok(by_credential)
+}
+
+// Convert credentials to the list format, with their custom fields and hidden values masked
+pub async fn credential_responses(
+    conn: &mut PgConnection,
+    credentials: Vec<Credential>,
+) -> Result<Vec<CredentialResponse>, AppError> {
+    let ids: Vec<Uuid> = credentials.iter().map(|c| c.id).collect();
+    let mut fields = credential_fields(conn, &ids).await?;
+
+    credentials
+        .into_iter()
+        .map(|credential| {
+            let credential_fields = fields.remove(&credential.id).unwrap_or_default();
+            let mut response = CredentialResponse::from(credential);
+            response.fields = credential_fields
+                .iter()
+                .map(|field| field_response(field, None))
+                .collect::<Result<_, _>>()?;
+            Ok(response)
+        })
+        .collect()
+}
+
+// Replace the custom fields of a credential. Fields that are kept retain their
+// creation time and only have updated_at bumped when something changed.
+async fn store_fields(conn: &mut PgConnection, credential_id: Uuid, rows: &[FieldRow]) -> Result<(), AppError> {
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+
+    sqlx::query("DELETE FROM credential_fields WHERE credential_id = $1 AND id <> ALL($2)")
+        .bind(credential_id)
+        .bind(&ids)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO credential_fields (id, credential_id, position, label, field_type, value)
+        SELECT f.id, $2, f.ord - 1, f.label, f.field_type, f.value
+        FROM UNNEST($1::uuid[], $3::text[], $4::text[], $5::text[])
+            WITH ORDINALITY AS f(id, label, field_type, value, ord)
+        ON CONFLICT (id) DO UPDATE SET
+            position = EXCLUDED.position,
+            label = EXCLUDED.label,
+            field_type = EXCLUDED.field_type,
+            value = EXCLUDED.value,
+            updated_at = CASE
+                WHEN (credential_fields.label, credential_fields.field_type, credential_fields.value)
+                    IS DISTINCT FROM (EXCLUDED.label, EXCLUDED.field_type, EXCLUDED.value)
+                THEN now()
+                ELSE credential_fields.updated_at
+            END
+        "#,
+    )
+    .bind(&ids)
+    .bind(credential_id)
+    .bind(rows.iter().map(|row| row.label.clone()).collect::<Vec<_>>())
+    .bind(rows.iter().map(|row| row.field_type.as_str()).collect::<Vec<_>>())
+    .bind(rows.iter().map(|row| row.value.clone()).collect::<Vec<_>>())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+// Move a login's current password into its history before it is replaced, unless it
+// is empty or the same as the new one, then drop entries beyond the configured depth.
+// Returns whether the password changes.
+async fn archive_password(
+    conn: &mut PgConnection,
+    credential_id: Uuid,
+    user_id: Uuid,
+    new_password: &str,
+) -> Result<bool, AppError> {
+    let current = sqlx::query_scalar::<_, String>("SELECT password FROM credentials WHERE id = $1 FOR UPDATE")
+        .bind(credential_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let plaintext = decrypt_password(&current, &user_id.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to decrypt password: {}", e)))?;
+    if plaintext == new_password {
+        return Ok(false);
+    }
+    if plaintext.is_empty() {
+        return Ok(true);
+    }
+
+    sqlx::query("INSERT INTO password_history (credential_id, password) VALUES ($1, $2)")
+        .bind(credential_id)
+        .bind(&current)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM password_history
+        WHERE credential_id = $1 AND id NOT IN (
+            SELECT id FROM password_history WHERE credential_id = $1
+            ORDER BY replaced_at DESC, id DESC
+            LIMIT $2
+        )
+        "#,
+    )
+    .bind(credential_id)
+    .bind(password_history_depth())
+    .execute(conn)
+    .await?;
+
+    Ok(true)
+}
+
+// Find a history entry of one of the user's credentials. Entries beyond the
+// configured depth are treated as gone even before they are trimmed.
+async fn history_entry(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    credential_id: Uuid,
+    entry_id: Uuid,
+) -> Result<PasswordHistoryEntry, AppError> {
+    sqlx::query_as::<_, PasswordHistoryEntry>(
+        r#"
+        SELECT h.* FROM password_history h
+        JOIN credentials c ON c.id = h.credential_id
+        WHERE h.id = $1 AND h.credential_id = $2 AND c.user_id = $3 AND h.id IN (
+            SELECT id FROM password_history WHERE credential_id = $2
+            ORDER BY replaced_at DESC, id DESC
+            LIMIT $4
+        )
+        "#,
+    )
+    .bind(entry_id)
+    .bind(credential_id)
+    .bind(user_id)
+    .bind(password_history_depth())
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Password history entry not found".to_string()))
+}
+
+// Recreate a credential from its trash snapshot in `category_id`, with its custom
+// fields and as much of its password history as the configured depth keeps
+pub async fn restore_credential(conn: &mut PgConnection, data: &Value, category_id: Uuid) -> Result<Uuid, AppError> {
+    let mut row = data.clone();
+    row["category_id"] = Value::String(category_id.to_string());
+
+    // Items trashed before fields or history existed have no list for them
+    let list = |key: &str| data.get(key).filter(|value| value.is_array()).cloned().unwrap_or(Value::Array(Vec::new()));
+
+    let credential_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO credentials SELECT * FROM jsonb_populate_record(NULL::credentials, $1) RETURNING id",
+    )
+    .bind(&row)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query("INSERT INTO credential_fields SELECT * FROM jsonb_populate_recordset(NULL::credential_fields, $1)")
+        .bind(list("fields"))
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO password_history
+        SELECT * FROM jsonb_populate_recordset(NULL::password_history, $1)
+        ORDER BY replaced_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(list("password_history"))
+    .bind(password_history_depth())
+    .execute(conn)
+    .await?;
+
+    Ok(credential_id)
+}
+
+// Get all credentials for a user, optionally of one type
 pub async fn get_credentials(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
+    Query(filter): Query<CredentialFilter>,
 ) -> Result<impl IntoResponse, AppError> {
     let credentials = sqlx::query_as::<_, Credential>(
-        "SELECT * FROM credentials WHERE user_id = $1 ORDER BY name",
+        "SELECT * FROM credentials WHERE user_id = $1 AND ($2::text IS NULL OR item_type = $2) ORDER BY name",
     )
     .bind(auth_user.user_id)
+    .bind(filter.item_type.map(ItemType::as_str))
     .fetch_all(&pool)
     .await?;
 
     // Convert to response format (without passwords)
-    let credentials_response: Vec<CredentialResponse> = credentials
-        .into_iter()
-        .map(|c| c.into())
-        .collect();
+    let mut conn = pool.acquire().await?;
+    let credentials_response = credential_responses(&mut conn, credentials).await?;
 
     Ok((StatusCode::OK, Json(credentials_response)))
 }
 
-// Get a single credential by ID
-pub async fn get_credential(
+// Get the credentials in one of the user's categories
+pub async fn get_credentials_by_category(
     auth_user: AuthUser,
     State(pool): State<PgPool>,
-    Path(credential_id): Path<Uuid>,
+    Path(category_id): Path<Uuid>,
+    Query(filter): Query<CredentialFilter>,
 ) -> Result<impl IntoResponse, AppError> {
-    let credential = sqlx::query_as::<_, Credential>(
-        "SELECT * FROM credentials WHERE id = $1 AND user_id = $2",
+    // Someone else's category looks the same as a missing one
+    check_category(&pool, auth_user.user_id, category_id).await?;
+
+    let credentials = sqlx::query_as::<_, Credential>(
+        r#"
+        SELECT * FROM credentials
+        WHERE category_id = $1 AND user_id = $2 AND ($3::text IS NULL OR item_type = $3)
+        ORDER BY name
+        "#,
     )
-    .bind(credential_id)
+    .bind(category_id)
     .bind(auth_user.user_id)
-    .fetch_optional(&pool)
-    .await?
-    .ok_or_else(|| AppError::NotFound("Credential not found".to_string()))?;
+    .bind(filter.item_type.map(ItemType::as_str))
+    .fetch_all(&pool)
+    .await?;
 
-    let response = CredentialResponse::from(credential);
-    Ok((StatusCode::OK, Json(response)))
+    // Convert to response format (without passwords)
+    let mut conn = pool.acquire().await?;
+    let credentials_response = credential_responses(&mut conn, credentials).await?;
+
+    Ok((StatusCode::OK, Json(credentials_response)))
 }
 
 // Get a single credential with its decrypted password
@@ -71,18 +317,38 @@ pub async fn get_credential_with_password(
     .await?
     .ok_or_else(|| AppError::NotFound("Credential not found".to_string()))?;
 
-    // Decrypt the password
-    let password = decrypt_password(&credential.password, &auth_user.user_id.to_string())
-        .map_err(|e| AppError::Internal(format!("Failed to decrypt password: {}", e)))?;
+    // Decrypt the password of a login, or the typed fields of anything else
+    let password = if credential.item_type == ItemType::Login.as_str() {
+        decrypt_password(&credential.password, &auth_user.user_id.to_string())
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt password: {}", e)))?
+    } else {
+        String::new()
+    };
+
+    let data = credential.payload.as_deref()
+        .map(|payload| open_item_data(payload, auth_user.user_id))
+        .transpose()?;
 
-    // Build the full response including the decrypted password
+    let mut conn = pool.acquire().await?;
+    let fields = credential_fields(&mut conn, &[credential.id]).await?
+        .remove(&credential.id)
+        .unwrap_or_default()
+        .iter()
+        .map(|field| field_response(field, Some(auth_user.user_id)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Build the full response including the decrypted secrets
     let response = CredentialWithPassword {
         id: credential.id,
         category_id: Some(credential.category_id),
+        item_type: credential.item_type,
+        metadata: credential.metadata,
         name: credential.name,
         website: credential.website,
         username: credential.username,
         password,
+        data,
+        fields,
         notes: credential.notes,
         created_at: credential.created_at,
         updated_at: credential.updated_at,
@@ -102,33 +368,60 @@ pub async fn create_credential(
         return Err(AppError::BadRequest("Credential name cannot be empty".to_string()));
     }
 
-    // Encrypt the password
-    let encrypted_password = encrypt_password(&payload.password, &auth_user.user_id.to_string())
-        .map_err(|e| AppError::Internal(format!("Failed to encrypt password: {}", e)))?;
+    check_category(&pool, auth_user.user_id, payload.category_id).await?;
 
-    // Create the credential
+    // Only logins have a username and password; other types carry typed data
+    let data = parse_item_data(payload.item_type, payload.data)?;
+    let (username, encrypted_password, sealed_data) = match &data {
+        None => {
+            let encrypted_password = encrypt_password(&payload.password, &auth_user.user_id.to_string())
+                .map_err(|e| AppError::Internal(format!("Failed to encrypt password: {}", e)))?;
+            (payload.username, encrypted_password, None)
+        }
+        Some(data) => {
+            if !payload.username.is_empty() || !payload.password.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "{} items keep their fields in data, not username and password",
+                    payload.item_type.as_str()
+                )));
+            }
+            (String::new(), String::new(), Some(seal_item_data(data, auth_user.user_id)?))
+        }
+    };
+
+    let fields = parse_fields(payload.fields, &[], auth_user.user_id)?;
+
+    // Create the credential and its custom fields
+    let mut tx = pool.begin().await?;
     let credential = sqlx::query_as::<_, Credential>(
         r#"
         INSERT INTO credentials (
-            user_id, category_id, name, username, 
-            password, website, notes
+            user_id, category_id, item_type, name, username, 
+            password, payload, metadata, website, notes
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         RETURNING *
         "#,
     )
     .bind(auth_user.user_id)
     .bind(payload.category_id)
+    .bind(payload.item_type.as_str())
     .bind(&payload.name)
-    .bind(&payload.username)
+    .bind(&username)
     .bind(&encrypted_password)
+    .bind(&sealed_data)
+    .bind(item_metadata(data.as_ref()))
     .bind(&payload.website)
     .bind(&payload.notes)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    store_fields(&mut tx, credential.id, &fields).await?;
+
     // Convert to response format
-    let response = CredentialResponse::from(credential);
+    let response = credential_responses(&mut tx, vec![credential]).await?.remove(0);
+    tx.commit().await?;
+
     Ok((StatusCode::CREATED, Json(response)))
 }
 
@@ -140,7 +433,7 @@ pub async fn update_credential(
     Json(payload): Json<UpdateCredential>,
 ) -> Result<impl IntoResponse, AppError> {
     // Check if credential exists and belongs to user
-    let _credential = sqlx::query_as::<_, Credential>(
+    let credential = sqlx::query_as::<_, Credential>(
         "SELECT * FROM credentials WHERE id = $1 AND user_id = $2",
     )
     .bind(credential_id)
@@ -156,6 +449,50 @@ pub async fn update_credential(
         }
     }
 
+    if let Some(category_id) = payload.category_id {
+        check_category(&pool, auth_user.user_id, category_id).await?;
+    }
+
+    // Logins change their username and password; other types replace their data
+    let is_login = credential.item_type == ItemType::Login.as_str();
+    if !is_login && (payload.username.is_some() || payload.password.is_some()) {
+        return Err(AppError::BadRequest(format!(
+            "{} items keep their fields in data, not username and password",
+            credential.item_type
+        )));
+    }
+
+    let data = match payload.data {
+        Some(data) => {
+            let item_type = credential.item_type.parse::<ItemType>().map_err(AppError::Internal)?;
+            parse_item_data(item_type, Some(data))?
+        }
+        None => None,
+    };
+
+    // Check the new custom fields against the current ones before writing anything
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT id FROM credentials WHERE id = $1 FOR UPDATE")
+        .bind(credential_id)
+        .execute(&mut *tx)
+        .await?;
+    let before = credential_snapshot(&mut tx, credential_id).await?;
+    let fields = match payload.fields {
+        Some(inputs) => {
+            let existing = credential_fields(&mut tx, &[credential_id]).await?
+                .remove(&credential_id)
+                .unwrap_or_default();
+            Some(parse_fields(inputs, &existing, auth_user.user_id)?)
+        }
+        None => None,
+    };
+
+    // Keep the password being replaced
+    let password_changed = match &payload.password {
+        Some(password) => archive_password(&mut tx, credential_id, auth_user.user_id, password).await?,
+        None => false,
+    };
+
     // Handle password encryption if provided
     let encrypted_password = if let Some(ref password) = payload.password {
         Some(encrypt_password(password, &auth_user.user_id.to_string())
@@ -196,6 +533,17 @@ pub async fn update_credential(
         param_count += 1;
     }
 
+    // Add the typed data and its summary if provided
+    if let Some(data) = &data {
+        if param_count > 1 {
+            query += ", ";
+        }
+        query += &format!("payload = ${}, metadata = ${}::jsonb", param_count, param_count + 1);
+        params.push(seal_item_data(data, auth_user.user_id)?);
+        params.push(item_metadata(Some(data)).to_string());
+        param_count += 2;
+    }
+
     // Add website if provided
     if let Some(website) = &payload.website {
         if param_count > 1 {
@@ -221,7 +569,7 @@ pub async fn update_credential(
         if param_count > 1 {
             query += ", ";
         }
-        query += &format!("category_id = ${}", param_count);
+        query += &format!("category_id = ${}::uuid", param_count);
         params.push(category_id.to_string());
         param_count += 1;
     }
@@ -233,8 +581,8 @@ pub async fn update_credential(
     query += &format!("updated_at = now() WHERE id = ${} AND user_id = ${}", 
         param_count, param_count + 1);
 
-    // Execute the query if any fields were provided
-    if param_count > 1 {
+    // Execute the query if any fields were provided; custom fields alone still touch updated_at
+    if param_count > 1 || fields.is_some() {
         let mut query_builder = sqlx::query(&query);
         
         // Add all the parameters
@@ -246,20 +594,30 @@ pub async fn update_credential(
         query_builder = query_builder.bind(credential_id).bind(auth_user.user_id);
         
         // Execute
-        query_builder.execute(&pool).await?;
+        query_builder.execute(&mut *tx).await?;
+    }
+
+    if let Some(fields) = &fields {
+        store_fields(&mut tx, credential_id, fields).await?;
     }
 
+    let after = credential_snapshot(&mut tx, credential_id).await?;
+    let change = ItemChange { before: &before, after: &after, password_changed };
+    record_revision(&mut tx, &auth_user, ITEM_CREDENTIAL, credential_id, change, None).await?;
+
     // Get the updated credential
     let updated_credential = sqlx::query_as::<_, Credential>(
         "SELECT * FROM credentials WHERE id = $1 AND user_id = $2",
     )
     .bind(credential_id)
     .bind(auth_user.user_id)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await?;
 
     // Return updated credential
-    let response = CredentialResponse::from(updated_credential);
+    let response = credential_responses(&mut tx, vec![updated_credential]).await?.remove(0);
+    tx.commit().await?;
+
     Ok((StatusCode::OK, Json(response)))
 }
 
@@ -284,4 +642,344 @@ pub async fn delete_credential(
     }
 
     Ok(StatusCode::NO_CONTENT)
-} 
\ No newline at end of file
+}
+
+// Reveal the value of one custom field, decrypting it if hidden
+pub async fn reveal_credential_field(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path((credential_id, field_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let field = sqlx::query_as::<_, CredentialField>(
+        r#"
+        SELECT f.* FROM credential_fields f
+        JOIN credentials c ON c.id = f.credential_id
+        WHERE f.id = $1 AND f.credential_id = $2 AND c.user_id = $3
+        "#,
+    )
+    .bind(field_id)
+    .bind(credential_id)
+    .bind(auth_user.user_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Field not found".to_string()))?;
+
+    let response = field_response(&field, Some(auth_user.user_id))?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// List the previous passwords of a credential, newest first, without revealing them
+pub async fn get_password_history(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(credential_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let credential_exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM credentials WHERE id = $1 AND user_id = $2)",
+    )
+    .bind(credential_id)
+    .bind(auth_user.user_id)
+    .fetch_one(&pool)
+    .await?;
+
+    if !credential_exists {
+        return Err(AppError::NotFound("Credential not found".to_string()));
+    }
+
+    let entries = sqlx::query_as::<_, PasswordHistoryEntry>(
+        r#"
+        SELECT * FROM password_history WHERE credential_id = $1
+        ORDER BY replaced_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(credential_id)
+    .bind(password_history_depth())
+    .fetch_all(&pool)
+    .await?;
+
+    let response: Vec<PasswordHistoryResponse> = entries
+        .into_iter()
+        .map(|entry| PasswordHistoryResponse {
+            id: entry.id,
+            password: None,
+            replaced_at: entry.replaced_at,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Reveal one previous password of a credential
+pub async fn reveal_password_history(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path((credential_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let entry = history_entry(&mut conn, auth_user.user_id, credential_id, entry_id).await?;
+
+    let password = decrypt_password(&entry.password, &auth_user.user_id.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to decrypt password: {}", e)))?;
+
+    let response = PasswordHistoryResponse {
+        id: entry.id,
+        password: Some(password),
+        replaced_at: entry.replaced_at,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Make a previous password current again. The password it replaces goes into the
+// history in its place, so a restore can itself be undone.
+pub async fn restore_password_history(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path((credential_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    let entry = history_entry(&mut tx, auth_user.user_id, credential_id, entry_id).await?;
+
+    let password = decrypt_password(&entry.password, &auth_user.user_id.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to decrypt password: {}", e)))?;
+
+    // Take the entry out first so trimming the history cannot drop it
+    sqlx::query("DELETE FROM password_history WHERE id = $1")
+        .bind(entry.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let before = credential_snapshot(&mut tx, credential_id).await?;
+    let password_changed = archive_password(&mut tx, credential_id, auth_user.user_id, &password).await?;
+
+    let credential = sqlx::query_as::<_, Credential>(
+        "UPDATE credentials SET password = $1, updated_at = now() WHERE id = $2 RETURNING *",
+    )
+    .bind(&entry.password)
+    .bind(credential_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let after = credential_snapshot(&mut tx, credential_id).await?;
+    let change = ItemChange { before: &before, after: &after, password_changed };
+    record_revision(&mut tx, &auth_user, ITEM_CREDENTIAL, credential_id, change, None).await?;
+
+    let response = credential_responses(&mut tx, vec![credential]).await?.remove(0);
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Put a credential back the way it was at one of its revisions, apart from its
+// password. The rollback is itself recorded as a new revision.
+pub async fn rollback_credential(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path((credential_id, revision_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM credentials WHERE id = $1 AND user_id = $2 FOR UPDATE",
+    )
+    .bind(credential_id)
+    .bind(auth_user.user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Credential not found".to_string()))?;
+
+    let revision = find_revision(&mut tx, auth_user.user_id, ITEM_CREDENTIAL, credential_id, revision_id).await?;
+    let snapshot: CredentialSnapshot = serde_json::from_value(revision.data)
+        .map_err(|e| AppError::Internal(format!("Failed to decode revision: {}", e)))?;
+
+    match check_category(&pool, auth_user.user_id, snapshot.category_id).await {
+        Err(AppError::NotFound(_)) => {
+            return Err(AppError::Conflict("The category of this revision no longer exists".to_string()));
+        }
+        result => result?,
+    }
+
+    let before = credential_snapshot(&mut tx, credential_id).await?;
+
+    // Revisions hold no passwords; the password history restores those
+    sqlx::query(
+        r#"
+        UPDATE credentials
+        SET category_id = $1, name = $2, username = $3, payload = $4,
+            metadata = $5, website = $6, notes = $7, updated_at = now()
+        WHERE id = $8
+        "#,
+    )
+    .bind(snapshot.category_id)
+    .bind(&snapshot.name)
+    .bind(&snapshot.username)
+    .bind(&snapshot.payload)
+    .bind(&snapshot.metadata)
+    .bind(&snapshot.website)
+    .bind(&snapshot.notes)
+    .bind(credential_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let fields: Vec<FieldRow> = snapshot
+        .fields
+        .into_iter()
+        .map(|field| FieldRow {
+            id: field.id,
+            label: field.label,
+            field_type: field.field_type,
+            value: field.value,
+        })
+        .collect();
+    store_fields(&mut tx, credential_id, &fields).await?;
+
+    let after = credential_snapshot(&mut tx, credential_id).await?;
+    let change = ItemChange { before: &before, after: &after, password_changed: false };
+    record_revision(&mut tx, &auth_user, ITEM_CREDENTIAL, credential_id, change, Some(revision.id)).await?;
+
+    let updated_credential = sqlx::query_as::<_, Credential>("SELECT * FROM credentials WHERE id = $1")
+        .bind(credential_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let response = credential_responses(&mut tx, vec![updated_credential]).await?.remove(0);
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::test_pool,
+        handlers::{response_status as status, test_credential, test_user},
+        jobs::trim_password_history,
+    };
+    use serde_json::json;
+
+    // A login in a new category of the user's
+    async fn login(pool: &PgPool, user: &AuthUser) -> Uuid {
+        let category_id = sqlx::query_scalar("INSERT INTO categories (user_id, name) VALUES ($1, 'Web') RETURNING id")
+            .bind(user.user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        test_credential(pool, user.user_id, "Login", category_id).await
+    }
+
+    // Add `count` earlier passwords, the newest first, and return their ids in that order
+    async fn add_history(pool: &PgPool, user: &AuthUser, credential_id: Uuid, count: i64) -> Vec<Uuid> {
+        let mut ids = Vec::new();
+        for age in 1..=count {
+            let password = encrypt_password(&format!("old {}", age), &user.user_id.to_string()).unwrap();
+            let id = sqlx::query_scalar(
+                r#"
+                INSERT INTO password_history (credential_id, password, replaced_at)
+                VALUES ($1, $2, now() - make_interval(mins => $3::int))
+                RETURNING id
+                "#,
+            )
+            .bind(credential_id)
+            .bind(password)
+            .bind(age as i32)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+        ids
+    }
+
+    async fn history_count(pool: &PgPool, credential_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM password_history WHERE credential_id = $1")
+            .bind(credential_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn updates_keep_only_the_configured_history_depth() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let credential_id = login(&pool, &user).await;
+        let depth = password_history_depth();
+
+        for n in 0..depth + 3 {
+            let payload = serde_json::from_value(json!({ "password": format!("new {}", n) })).unwrap();
+            let result = update_credential(user.clone(), State(pool.clone()), Path(credential_id), Json(payload)).await;
+            assert_eq!(status(result), StatusCode::OK);
+        }
+        assert_eq!(history_count(&pool, credential_id).await, depth);
+
+        // Saving the same password again does not push anything out
+        let payload = serde_json::from_value(json!({ "password": format!("new {}", depth + 2) })).unwrap();
+        let result = update_credential(user.clone(), State(pool.clone()), Path(credential_id), Json(payload)).await;
+        assert_eq!(status(result), StatusCode::OK);
+        let newest = sqlx::query_scalar::<_, String>(
+            "SELECT password FROM password_history WHERE credential_id = $1 ORDER BY replaced_at DESC, id DESC LIMIT 1",
+        )
+        .bind(credential_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let newest = decrypt_password(&newest, &user.user_id.to_string()).unwrap();
+        assert_eq!(newest, format!("new {}", depth + 1));
+    }
+
+    #[tokio::test]
+    async fn the_trim_job_drops_entries_beyond_the_depth() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let credential_id = login(&pool, &user).await;
+        let depth = password_history_depth();
+        let ids = add_history(&pool, &user, credential_id, depth + 2).await;
+
+        trim_password_history(&pool).await.unwrap();
+
+        let kept = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM password_history WHERE credential_id = $1 ORDER BY replaced_at DESC",
+        )
+        .bind(credential_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(kept, ids[..depth as usize]);
+    }
+
+    #[tokio::test]
+    async fn entries_beyond_the_depth_cannot_be_revealed_or_restored() {
+        let Some(pool) = test_pool().await else { return };
+        let user = test_user(&pool).await;
+        let credential_id = login(&pool, &user).await;
+        let depth = password_history_depth();
+        let ids = add_history(&pool, &user, credential_id, depth + 1).await;
+        let (newest, oldest) = (ids[0], ids[depth as usize]);
+
+        let result = reveal_password_history(user.clone(), State(pool.clone()), Path((credential_id, oldest))).await;
+        assert_eq!(status(result), StatusCode::NOT_FOUND);
+        let result = restore_password_history(user.clone(), State(pool.clone()), Path((credential_id, oldest))).await;
+        assert_eq!(status(result), StatusCode::NOT_FOUND);
+
+        let result = reveal_password_history(user.clone(), State(pool.clone()), Path((credential_id, newest))).await;
+        assert_eq!(status(result), StatusCode::OK);
+        let result = restore_password_history(user.clone(), State(pool.clone()), Path((credential_id, newest))).await;
+        assert_eq!(status(result), StatusCode::OK);
+
+        // The restored entry became the password and the one it replaced took its place
+        let password = sqlx::query_scalar::<_, String>("SELECT password FROM credentials WHERE id = $1")
+            .bind(credential_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(decrypt_password(&password, &user.user_id.to_string()).unwrap(), "old 1");
+        assert!(history_count(&pool, credential_id).await <= depth);
+
+        // Another user cannot reach the history at all
+        let other = test_user(&pool).await;
+        let result = reveal_password_history(other, State(pool.clone()), Path((credential_id, ids[1]))).await;
+        assert_eq!(status(result), StatusCode::NOT_FOUND);
+    }
+}
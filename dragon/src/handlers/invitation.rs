@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use sqlx::{PgConnection, PgPool};
+use std::env;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        policy::RegistrationPolicy,
+        tokens::{generate_token, hash_token},
+    },
+    errors::AppError,
+    mail::{frontend_url, send_best_effort, Email},
+    middleware::auth::AuthUser,
+    models::{
+        invitation::{CreateInvitation, CreatedInvitation, Invitation, InvitationResponse},
+        user::ROLE_ADMIN,
+    },
+};
+
+// Invitation lifetimes in days
+const DEFAULT_INVITATION_DAYS: i64 = 7;
+const MAX_INVITATION_DAYS: i64 = 30;
+
+// Mark an invitation as used, failing if it is unknown, used, expired or for another address
+pub async fn claim_invitation(
+    conn: &mut PgConnection,
+    token: &str,
+    email: &str,
+) -> Result<Invitation, AppError> {
+    sqlx::query_as::<_, Invitation>(
+        r#"
+        UPDATE invitations SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+          AND (email IS NULL OR lower(email) = lower($2))
+        RETURNING *
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(email)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::Forbidden("Invalid or expired invitation".to_string()))
+}
+
+// Create an invitation, emailing it when it is addressed to someone
+pub async fn create_invitation(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateInvitation>,
+) -> Result<impl IntoResponse, AppError> {
+    // INVITATIONS_ADMIN_ONLY=true keeps invitations in admins' hands
+    let admin_only = env::var("INVITATIONS_ADMIN_ONLY").map(|v| v == "true").unwrap_or(false);
+    if admin_only && auth_user.role != ROLE_ADMIN {
+        return Err(AppError::Forbidden("Only administrators can invite users".to_string()));
+    }
+
+    let email = payload.email
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+
+    // Regular users may only invite addresses that could self-register
+    if let Some(ref email) = email {
+        if auth_user.role != ROLE_ADMIN && !RegistrationPolicy::from_env().email_allowed(email) {
+            return Err(AppError::Forbidden("Invitations are not open to this email domain".to_string()));
+        }
+    }
+
+    let days = payload.expires_in_days.unwrap_or(DEFAULT_INVITATION_DAYS);
+    if !(1..=MAX_INVITATION_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!(
+            "Invitations can last between 1 and {} days", MAX_INVITATION_DAYS
+        )));
+    }
+
+    let token = generate_token();
+    let invitation = sqlx::query_as::<_, Invitation>(
+        r#"
+        INSERT INTO invitations (token_hash, email, created_by, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(hash_token(&token))
+    .bind(&email)
+    .bind(auth_user.user_id)
+    .bind(OffsetDateTime::now_utc() + Duration::days(days))
+    .fetch_one(&pool)
+    .await?;
+
+    let link = frontend_url(&format!("/register?invite={}", token));
+
+    if let Some(ref email) = email {
+        send_best_effort(Email {
+            to: email.clone(),
+            subject: "You're invited to DragonFruit".to_string(),
+            body: format!(
+                "You have been invited to create a DragonFruit account. \
+                 Open this link within {} days to register:\n\n{}\n",
+                days, link,
+            ),
+        })
+        .await;
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedInvitation {
+            invitation: InvitationResponse::from(invitation),
+            token,
+            link,
+        }),
+    ))
+}
+
+// List the invitations the current user has created
+pub async fn get_invitations(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let invitations = sqlx::query_as::<_, Invitation>(
+        "SELECT * FROM invitations WHERE created_by = $1 ORDER BY created_at DESC",
+    )
+    .bind(auth_user.user_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let invitations_response: Vec<InvitationResponse> = invitations
+        .into_iter()
+        .map(InvitationResponse::from)
+        .collect();
+
+    Ok((StatusCode::OK, Json(invitations_response)))
+}
+
+// Revoke an unused invitation
+pub async fn revoke_invitation(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    // Admins may revoke anyone's invitation
+    let result = sqlx::query(
+        r#"
+        DELETE FROM invitations
+        WHERE id = $1 AND used_at IS NULL AND (created_by = $2 OR $3)
+        "#,
+    )
+    .bind(invitation_id)
+    .bind(auth_user.user_id)
+    .bind(auth_user.role == ROLE_ADMIN)
+    .execute(&pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Invitation not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
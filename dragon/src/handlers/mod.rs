@@ -1,5 +1,73 @@
+pub mod account;
+pub mod admin;
 pub mod auth;
 pub mod category;
 pub mod credential;
+pub mod invitation;
+pub mod oauth;
+pub mod revision;
+pub mod security;
 
-pub use auth::*; 
\ No newline at end of file
+pub use account::*;
+pub use admin::*;
+pub use auth::*;
+pub use category::*;
+pub use credential::*;
+pub use invitation::*;
+pub use oauth::*;
+pub use revision::*;
+pub use security::*;
+
+// A new account for handler tests to act as
+#[cfg(test)]
+pub async fn test_user(pool: &sqlx::PgPool) -> crate::middleware::auth::AuthUser {
+    use crate::{auth::jwt::TokenSubject, middleware::auth::AuthUser, models::user::ROLE_USER};
+
+    // Vault secrets are encrypted with keys derived from JWT_SECRET
+    if std::env::var("JWT_SECRET").is_err() {
+        std::env::set_var("JWT_SECRET", "test-secret");
+    }
+
+    let name = format!("test{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+    let user_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO users (username, email, password_hash) VALUES ($1, $2, '') RETURNING id",
+    )
+    .bind(&name)
+    .bind(format!("{}@example.org", name))
+    .fetch_one(pool)
+    .await
+    .expect("test user");
+
+    AuthUser {
+        user_id,
+        role: ROLE_USER.to_string(),
+        token: TokenSubject { user_id, token_version: 0, token_id: None, expires_at: 0 },
+    }
+}
+
+// A login with the password "secret", filed without going through the handlers
+#[cfg(test)]
+pub async fn test_credential(pool: &sqlx::PgPool, user_id: uuid::Uuid, name: &str, category_id: uuid::Uuid) -> uuid::Uuid {
+    let password = crate::crypto::encrypt_password("secret", &user_id.to_string()).unwrap();
+    sqlx::query_scalar("INSERT INTO credentials (user_id, name, password, category_id) VALUES ($1, $2, $3, $4) RETURNING id")
+        .bind(user_id)
+        .bind(name)
+        .bind(password)
+        .bind(category_id)
+        .fetch_one(pool)
+        .await
+        .expect("test credential")
+}
+
+// The status a handler result is answered with
+#[cfg(test)]
+pub fn response_status(
+    result: Result<impl axum::response::IntoResponse, crate::errors::AppError>,
+) -> axum::http::StatusCode {
+    use axum::response::IntoResponse;
+
+    match result {
+        Ok(response) => response.into_response().status(),
+        Err(e) => e.into_response().status(),
+    }
+}
@@ -0,0 +1,323 @@
+use axum::{
+    extract::{Form, Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use rand::Rng;
+use sqlx::PgPool;
+use std::env;
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    auth::{
+        jwt::{create_token, TokenConfig},
+        tokens::{generate_token, hash_token},
+    },
+    errors::AppError,
+    handlers::{
+        auth::check_account_access,
+        security::{record_failed_login, record_successful_login, FACTOR_DEVICE_CODE, FAILURE_INTERNAL},
+    },
+    mail::frontend_url,
+    middleware::{auth::AuthUser, client::ClientInfo},
+    models::{
+        device_authorization::{
+            DeviceAuthorization, DeviceCodeRequest, DeviceCodeResponse, DeviceDecision, DeviceTokenRequest,
+            UserCodeQuery, STATUS_APPROVED, STATUS_DENIED, STATUS_ISSUED, STATUS_PENDING,
+        },
+        user::{User, UserResponse},
+    },
+};
+
+// grant_type for polling with a device code
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+// How long a device has to get approved, and how often it may poll
+const DEVICE_CODE_LIFETIME: Duration = Duration::minutes(10);
+const DEFAULT_POLL_INTERVAL_SECS: i32 = 5;
+const SLOW_DOWN_STEP_SECS: i32 = 5;
+
+// User codes avoid vowels and look-alike characters (RFC 8628 section 6.1)
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+
+// Longest client ID and device name we keep
+const MAX_CLIENT_FIELD_LENGTH: usize = 255;
+
+// Error responses from RFC 6749 section 5.2 and RFC 8628 section 3.5
+struct OAuthError {
+    error: &'static str,
+    description: String,
+}
+
+impl OAuthError {
+    fn new(error: &'static str, description: &str) -> Self {
+        Self { error, description: description.to_string() }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({
+            "error": self.error,
+            "error_description": self.description,
+        }));
+
+        (StatusCode::BAD_REQUEST, body).into_response()
+    }
+}
+
+// A random user code, shown as XXXX-XXXX
+fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    let code: String = (0..USER_CODE_LENGTH)
+        .map(|_| USER_CODE_CHARSET[rng.gen_range(0..USER_CODE_CHARSET.len())] as char)
+        .collect();
+    format!("{}-{}", &code[..USER_CODE_LENGTH / 2], &code[USER_CODE_LENGTH / 2..])
+}
+
+// Users may type the code in any case, with or without the dash
+fn hash_user_code(user_code: &str) -> String {
+    let normalized: String = user_code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+// Client IDs allowed by OAUTH_CLIENT_IDS (comma-separated); any when unset
+fn client_allowed(client_id: &str) -> bool {
+    match env::var("OAUTH_CLIENT_IDS") {
+        Ok(ids) if !ids.trim().is_empty() => ids.split(',').any(|id| id.trim() == client_id),
+        _ => true,
+    }
+}
+
+// Start a device authorization: the device shows the user code and polls for tokens
+pub async fn request_device_code(
+    State(pool): State<PgPool>,
+    client: ClientInfo,
+    Form(payload): Form<DeviceCodeRequest>,
+) -> Result<Response, AppError> {
+    let client_id = payload.client_id.trim();
+    if client_id.is_empty() || client_id.len() > MAX_CLIENT_FIELD_LENGTH || !client_allowed(client_id) {
+        return Ok(OAuthError::new("invalid_client", "Unknown client").into_response());
+    }
+
+    // Scopes are not supported; every token has the account's full access
+    if payload.scope.as_deref().is_some_and(|scope| !scope.trim().is_empty()) {
+        return Ok(OAuthError::new("invalid_scope", "Scopes are not supported").into_response());
+    }
+
+    let device_name: Option<String> = payload.device_name.as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.chars().take(MAX_CLIENT_FIELD_LENGTH).collect());
+
+    let device_code = generate_token();
+    let user_code = generate_user_code();
+
+    sqlx::query(
+        r#"
+        INSERT INTO device_authorizations
+            (device_code_hash, user_code_hash, client_id, device_name, ip, user_agent, interval_secs, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(hash_token(&device_code))
+    .bind(hash_user_code(&user_code))
+    .bind(client_id)
+    .bind(&device_name)
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .bind(&client.user_agent)
+    .bind(DEFAULT_POLL_INTERVAL_SECS)
+    .bind(OffsetDateTime::now_utc() + DEVICE_CODE_LIFETIME)
+    .execute(&pool)
+    .await?;
+
+    let response = DeviceCodeResponse {
+        device_code,
+        verification_uri: frontend_url("/device"),
+        verification_uri_complete: frontend_url(&format!("/device?user_code={}", user_code)),
+        user_code,
+        expires_in: DEVICE_CODE_LIFETIME.whole_seconds(),
+        interval: DEFAULT_POLL_INTERVAL_SECS,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+// Poll for tokens with a device code
+pub async fn exchange_device_code(
+    State(pool): State<PgPool>,
+    client: ClientInfo,
+    Form(payload): Form<DeviceTokenRequest>,
+) -> Result<Response, AppError> {
+    if payload.grant_type != DEVICE_CODE_GRANT {
+        return Ok(OAuthError::new("unsupported_grant_type", "Only the device code grant is supported").into_response());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let Some(authorization) = sqlx::query_as::<_, DeviceAuthorization>(
+        "SELECT * FROM device_authorizations WHERE device_code_hash = $1 AND client_id = $2 FOR UPDATE",
+    )
+    .bind(hash_token(&payload.device_code))
+    .bind(&payload.client_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(OAuthError::new("invalid_grant", "Unknown device code").into_response());
+    };
+
+    let now = OffsetDateTime::now_utc();
+    if authorization.expires_at <= now {
+        return Ok(OAuthError::new("expired_token", "The device code has expired").into_response());
+    }
+
+    // Devices that poll too fast have to back off for good
+    let too_fast = authorization.last_polled_at
+        .is_some_and(|polled| now - polled < Duration::seconds(authorization.interval_secs.into()));
+    let interval_secs = authorization.interval_secs + if too_fast { SLOW_DOWN_STEP_SECS } else { 0 };
+
+    sqlx::query("UPDATE device_authorizations SET last_polled_at = $2, interval_secs = $3 WHERE id = $1")
+        .bind(authorization.id)
+        .bind(now)
+        .bind(interval_secs)
+        .execute(&mut *tx)
+        .await?;
+
+    if too_fast {
+        tx.commit().await?;
+        return Ok(OAuthError::new("slow_down", "Polling too frequently").into_response());
+    }
+
+    let error = match authorization.status.as_str() {
+        STATUS_PENDING => Some(OAuthError::new("authorization_pending", "Waiting for the user to approve")),
+        STATUS_DENIED => Some(OAuthError::new("access_denied", "The user denied the request")),
+        STATUS_APPROVED => None,
+        _ => Some(OAuthError::new("invalid_grant", "The device code has already been used")),
+    };
+
+    if let Some(error) = error {
+        tx.commit().await?;
+        return Ok(error.into_response());
+    }
+
+    let user_id = authorization.user_id
+        .ok_or_else(|| AppError::Internal("Approved device authorization has no user".to_string()))?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    // The same account checks as a password login, against the polling client
+    if let Err(failure) = check_account_access(&pool, &user, &client).await {
+        if failure.reason == FAILURE_INTERNAL {
+            return Err(failure.error);
+        }
+
+        tx.commit().await?;
+        record_failed_login(&pool, &user.username, &client, failure.reason).await;
+        let description = format!("The account may not sign in here ({})", failure.reason);
+        return Ok(OAuthError::new("access_denied", &description).into_response());
+    }
+
+    // A device code yields tokens once
+    sqlx::query("UPDATE device_authorizations SET status = $2 WHERE id = $1")
+        .bind(authorization.id)
+        .bind(STATUS_ISSUED)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    record_successful_login(&pool, &user, &client, FACTOR_DEVICE_CODE).await?;
+
+    let token_config = TokenConfig::default();
+    let token = create_token(user.id, user.token_version, &token_config)?;
+
+    // The OAuth fields plus the same body as a password login
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "access_token": token,
+            "token_type": "Bearer",
+            "expires_in": token_config.expires_in,
+            "token": token,
+            "user": UserResponse::from(user)
+        })),
+    )
+        .into_response())
+}
+
+// Show a pending request so the user can check it is theirs before approving
+pub async fn get_device_authorization(
+    _auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Query(query): Query<UserCodeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+        r#"
+        SELECT * FROM device_authorizations
+        WHERE user_code_hash = $1 AND status = $2 AND expires_at > now()
+        "#,
+    )
+    .bind(hash_user_code(&query.user_code))
+    .bind(STATUS_PENDING)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Unknown or expired code".to_string()))?;
+
+    Ok((StatusCode::OK, Json(authorization)))
+}
+
+// Approve or deny a pending request, recording who decided
+pub async fn decide_device_authorization(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Json(payload): Json<DeviceDecision>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = if payload.approve { STATUS_APPROVED } else { STATUS_DENIED };
+
+    let authorization = sqlx::query_as::<_, DeviceAuthorization>(
+        r#"
+        UPDATE device_authorizations
+        SET status = $3, user_id = $2, decided_at = now()
+        WHERE user_code_hash = $1 AND status = $4 AND expires_at > now()
+        RETURNING *
+        "#,
+    )
+    .bind(hash_user_code(&payload.user_code))
+    .bind(auth_user.user_id)
+    .bind(status)
+    .bind(STATUS_PENDING)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Unknown or expired code".to_string()))?;
+
+    tracing::info!(
+        user_id = %auth_user.user_id,
+        client_id = %authorization.client_id,
+        status,
+        "Device authorization decided"
+    );
+
+    Ok((StatusCode::OK, Json(authorization)))
+}
+
+// Devices the current user has approved or denied, newest first
+pub async fn get_device_authorizations(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let authorizations = sqlx::query_as::<_, DeviceAuthorization>(
+        "SELECT * FROM device_authorizations WHERE user_id = $1 ORDER BY decided_at DESC",
+    )
+    .bind(auth_user.user_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(authorizations)))
+}
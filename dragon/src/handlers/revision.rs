@@ -0,0 +1,421 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+use crate::{
+    crypto::decrypt_password,
+    errors::AppError,
+    middleware::auth::AuthUser,
+    models::{
+        revision::{FieldChange, Revision, RevisionDiff, RevisionDiffQuery, RevisionResponse},
+        trash::{ITEM_CATEGORY, ITEM_CREDENTIAL},
+    },
+    vault::open_item_data,
+};
+
+// Credential columns whose values are encrypted
+const SECRET_CREDENTIAL_FIELDS: &[&str] = &["payload"];
+
+// Listed in changed_fields when a login's password changed; the value is never stored
+const PASSWORD_FIELD: &str = "password";
+
+// The state of a credential as a revision records it. A login's password is left
+// out: previous passwords are only kept in the password history, which is limited
+// to PASSWORD_HISTORY_DEPTH entries.
+pub async fn credential_snapshot(conn: &mut PgConnection, credential_id: Uuid) -> Result<Value, AppError> {
+    let snapshot = sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT jsonb_build_object(
+            'category_id', c.category_id,
+            'name', c.name,
+            'username', c.username,
+            'payload', c.payload,
+            'metadata', c.metadata,
+            'website', c.website,
+            'notes', c.notes,
+            'fields', COALESCE(
+                (
+                    SELECT jsonb_agg(
+                        jsonb_build_object('id', f.id, 'label', f.label, 'type', f.field_type, 'value', f.value)
+                        ORDER BY f.position
+                    )
+                    FROM credential_fields f WHERE f.credential_id = c.id
+                ),
+                '[]'::jsonb
+            )
+        )
+        FROM credentials c WHERE c.id = $1
+        "#,
+    )
+    .bind(credential_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(snapshot)
+}
+
+// The state of a category as a revision records it
+pub async fn category_snapshot(conn: &mut PgConnection, category_id: Uuid) -> Result<Value, AppError> {
+    let snapshot = sqlx::query_scalar::<_, Value>(
+        r#"
+        SELECT jsonb_build_object(
+            'name', name,
+            'description', description,
+            'parent_id', parent_id,
+            'color', color,
+            'icon', icon,
+            'collapsed', collapsed
+        )
+        FROM categories WHERE id = $1
+        "#,
+    )
+    .bind(category_id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(snapshot)
+}
+
+// Decrypt the secret parts of a snapshot. Encrypting a value twice gives different
+// ciphertexts, so changes are found by comparing what they decrypt to.
+fn reveal(item_type: &str, data: &Value, user_id: Uuid) -> Result<Value, AppError> {
+    let mut data = data.clone();
+    if item_type != ITEM_CREDENTIAL {
+        return Ok(data);
+    }
+
+    let decrypt = |value: &str| {
+        decrypt_password(value, &user_id.to_string())
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt revision: {}", e)))
+    };
+
+    if let Some(payload) = data["payload"].as_str() {
+        data["payload"] = open_item_data(payload, user_id)?;
+    }
+
+    if let Some(fields) = data["fields"].as_array_mut() {
+        for field in fields.iter_mut().filter(|field| field["type"] == "hidden") {
+            if let Some(value) = field["value"].as_str() {
+                field["value"] = Value::String(decrypt(value)?);
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+// Blank out the encrypted parts of a snapshot before it is shown
+fn mask(item_type: &str, data: &Value) -> Value {
+    let mut data = data.clone();
+    if item_type != ITEM_CREDENTIAL {
+        return data;
+    }
+
+    for field in SECRET_CREDENTIAL_FIELDS {
+        data[*field] = Value::Null;
+    }
+
+    if let Some(fields) = data["fields"].as_array_mut() {
+        for field in fields.iter_mut().filter(|field| field["type"] == "hidden") {
+            field["value"] = Value::Null;
+        }
+    }
+
+    data
+}
+
+// Names of the top-level fields that differ between two revealed snapshots
+fn changed_fields(before: &Value, after: &Value) -> Vec<String> {
+    let keys: BTreeSet<&String> = before
+        .as_object()
+        .into_iter()
+        .chain(after.as_object())
+        .flat_map(|object| object.keys())
+        .collect();
+
+    keys.into_iter()
+        .filter(|key| before.get(key.as_str()) != after.get(key.as_str()))
+        .cloned()
+        .collect()
+}
+
+// An item's snapshots from before and after a change
+pub struct ItemChange<'a> {
+    pub before: &'a Value,
+    pub after: &'a Value,
+    // Login passwords are not part of snapshots, so the caller says when one changed
+    pub password_changed: bool,
+}
+
+// Record a change the user made to one of their items. The first change recorded
+// for an item also stores the earlier state as version 1. Nothing is recorded when
+// no value changed.
+pub async fn record_revision(
+    conn: &mut PgConnection,
+    auth_user: &AuthUser,
+    item_type: &str,
+    item_id: Uuid,
+    change: ItemChange<'_>,
+    rollback_of: Option<Uuid>,
+) -> Result<Option<Revision>, AppError> {
+    let ItemChange { before, after, password_changed } = change;
+
+    // Users only edit their own items, so the owner is also the author
+    let (user_id, changed_by) = (auth_user.user_id, auth_user.user_id);
+    let mut changed = changed_fields(&reveal(item_type, before, user_id)?, &reveal(item_type, after, user_id)?);
+    if password_changed {
+        changed.push(PASSWORD_FIELD.to_string());
+        changed.sort();
+    }
+    if changed.is_empty() {
+        return Ok(None);
+    }
+
+    let latest = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT MAX(version) FROM revisions WHERE item_type = $1 AND item_id = $2",
+    )
+    .bind(item_type)
+    .bind(item_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let insert = r#"
+        INSERT INTO revisions (user_id, item_type, item_id, version, changed_by, changed_fields, data, rollback_of)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+    "#;
+
+    let version = match latest {
+        Some(version) => version + 1,
+        None => {
+            sqlx::query(insert)
+                .bind(user_id)
+                .bind(item_type)
+                .bind(item_id)
+                .bind(1)
+                .bind(changed_by)
+                .bind(Vec::<String>::new())
+                .bind(before)
+                .bind(None::<Uuid>)
+                .execute(&mut *conn)
+                .await?;
+            2
+        }
+    };
+
+    let revision = sqlx::query_as::<_, Revision>(insert)
+        .bind(user_id)
+        .bind(item_type)
+        .bind(item_id)
+        .bind(version)
+        .bind(changed_by)
+        .bind(&changed)
+        .bind(after)
+        .bind(rollback_of)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(Some(revision))
+}
+
+// Find one revision of one of the user's items
+pub async fn find_revision(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    item_type: &str,
+    item_id: Uuid,
+    revision_id: Uuid,
+) -> Result<Revision, AppError> {
+    sqlx::query_as::<_, Revision>(
+        "SELECT * FROM revisions WHERE id = $1 AND user_id = $2 AND item_type = $3 AND item_id = $4",
+    )
+    .bind(revision_id)
+    .bind(user_id)
+    .bind(item_type)
+    .bind(item_id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Revision not found".to_string()))
+}
+
+// Whether the user still has the item
+async fn item_exists(conn: &mut PgConnection, user_id: Uuid, item_type: &str, item_id: Uuid) -> Result<bool, AppError> {
+    let query = if item_type == ITEM_CATEGORY {
+        "SELECT EXISTS(SELECT 1 FROM categories WHERE id = $1 AND user_id = $2)"
+    } else {
+        "SELECT EXISTS(SELECT 1 FROM credentials WHERE id = $1 AND user_id = $2)"
+    };
+
+    let exists = sqlx::query_scalar::<_, bool>(query)
+        .bind(item_id)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(exists)
+}
+
+// Revisions of an item, newest first. They stay listed after the item is deleted.
+async fn list_revisions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    item_type: &str,
+    item_id: Uuid,
+) -> Result<Vec<RevisionResponse>, AppError> {
+    let revisions = sqlx::query_as::<_, Revision>(
+        "SELECT * FROM revisions WHERE user_id = $1 AND item_type = $2 AND item_id = $3 ORDER BY version DESC",
+    )
+    .bind(user_id)
+    .bind(item_type)
+    .bind(item_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if revisions.is_empty() && !item_exists(conn, user_id, item_type, item_id).await? {
+        return Err(AppError::NotFound(format!("{} not found", capitalize(item_type))));
+    }
+
+    Ok(revisions.into_iter().map(RevisionResponse::from).collect())
+}
+
+// Field-level differences between two revisions of an item, or between one and the current state
+async fn diff_revisions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    item_type: &str,
+    item_id: Uuid,
+    query: RevisionDiffQuery,
+) -> Result<RevisionDiff, AppError> {
+    let from = find_revision(&mut *conn, user_id, item_type, item_id, query.from).await?;
+    let (from_version, from) = (from.version, from.data);
+
+    // Comparing with the current state covers every later revision
+    let mut to_version = i32::MAX;
+    let to = match query.to {
+        Some(to) => {
+            let to = find_revision(&mut *conn, user_id, item_type, item_id, to).await?;
+            to_version = to.version;
+            to.data
+        }
+        None if !item_exists(&mut *conn, user_id, item_type, item_id).await? => {
+            return Err(AppError::NotFound(format!("{} not found", capitalize(item_type))));
+        }
+        None if item_type == ITEM_CATEGORY => category_snapshot(conn, item_id).await?,
+        None => credential_snapshot(conn, item_id).await?,
+    };
+
+    let mut changed = changed_fields(&reveal(item_type, &from, user_id)?, &reveal(item_type, &to, user_id)?);
+    if password_changed_between(&mut *conn, item_type, item_id, from_version, to_version).await? {
+        changed.push(PASSWORD_FIELD.to_string());
+        changed.sort();
+    }
+    let (from, to) = (mask(item_type, &from), mask(item_type, &to));
+
+    // A change is secret when nothing differs once encrypted values are hidden
+    let changes = changed
+        .into_iter()
+        .map(|field| FieldChange {
+            from: from[field.as_str()].clone(),
+            to: to[field.as_str()].clone(),
+            secret: from[field.as_str()] == to[field.as_str()],
+            field,
+        })
+        .collect();
+
+    Ok(RevisionDiff {
+        from: query.from,
+        to: query.to,
+        changes,
+    })
+}
+
+// Whether a revision between two versions, in either order, recorded a password change
+async fn password_changed_between(
+    conn: &mut PgConnection,
+    item_type: &str,
+    item_id: Uuid,
+    from_version: i32,
+    to_version: i32,
+) -> Result<bool, AppError> {
+    let changed = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM revisions
+            WHERE item_type = $1 AND item_id = $2 AND $3 = ANY(changed_fields) AND version > $4 AND version <= $5
+        )
+        "#,
+    )
+    .bind(item_type)
+    .bind(item_id)
+    .bind(PASSWORD_FIELD)
+    .bind(from_version.min(to_version))
+    .bind(from_version.max(to_version))
+    .fetch_one(conn)
+    .await?;
+
+    Ok(changed)
+}
+
+fn capitalize(item_type: &str) -> String {
+    let mut chars = item_type.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+// List the revisions of a credential
+pub async fn get_credential_revisions(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(credential_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let revisions = list_revisions(&mut conn, auth_user.user_id, ITEM_CREDENTIAL, credential_id).await?;
+
+    Ok((StatusCode::OK, Json(revisions)))
+}
+
+// Compare two revisions of a credential
+pub async fn diff_credential_revisions(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(credential_id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let diff = diff_revisions(&mut conn, auth_user.user_id, ITEM_CREDENTIAL, credential_id, query).await?;
+
+    Ok((StatusCode::OK, Json(diff)))
+}
+
+// List the revisions of a category
+pub async fn get_category_revisions(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(category_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let revisions = list_revisions(&mut conn, auth_user.user_id, ITEM_CATEGORY, category_id).await?;
+
+    Ok((StatusCode::OK, Json(revisions)))
+}
+
+// Compare two revisions of a category
+pub async fn diff_category_revisions(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(category_id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let diff = diff_revisions(&mut conn, auth_user.user_id, ITEM_CATEGORY, category_id, query).await?;
+
+    Ok((StatusCode::OK, Json(diff)))
+}
@@ -0,0 +1,394 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use ipnet::IpNet;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        devices::revoke_trusted_devices,
+        identity::normalize_identifier,
+        network::{parse_cidr, IpAllowlist, MANAGED_BY_USER},
+    },
+    errors::AppError,
+    handlers::account::reauthenticate,
+    mail::{send_best_effort, Email},
+    middleware::{auth::AuthUser, client::ClientInfo},
+    models::user::{
+        ChangeIpAllowlist, CreateIpAllowlistEntry, IpAllowlistEntry, LoginEvent, LoginHistoryQuery,
+        Reauthenticate, TrustedDevice, User,
+    },
+};
+
+// Factors recorded for a successful sign-in
+pub const FACTOR_PASSWORD: &str = "password";
+pub const FACTOR_PASSWORD_TOTP: &str = "password+totp";
+pub const FACTOR_PASSWORD_EMAIL: &str = "password+email_code";
+pub const FACTOR_PASSWORD_DEVICE: &str = "password+trusted_device";
+pub const FACTOR_DEVICE_CODE: &str = "device_code"; // Approved from a signed-in session
+
+// Why a sign-in failed, as kept in the login history. Only these codes are stored,
+// so no error text reaches the user through their history.
+pub const FAILURE_INVALID_PASSWORD: &str = "invalid_password";
+pub const FAILURE_ACCOUNT_DISABLED: &str = "account_disabled";
+pub const FAILURE_IP_NOT_ALLOWED: &str = "ip_not_allowed";
+pub const FAILURE_RESET_REQUIRED: &str = "reset_required";
+pub const FAILURE_EMAIL_UNVERIFIED: &str = "email_unverified";
+pub const FAILURE_INVALID_SECOND_FACTOR: &str = "invalid_second_factor";
+pub const FAILURE_SECOND_FACTOR_REQUIRED: &str = "second_factor_required";
+pub const FAILURE_INTERNAL: &str = "internal";
+
+// A failed sign-in: the error returned to the client and the code recorded for it
+#[derive(Debug)]
+pub struct LoginFailure {
+    pub error: AppError,
+    pub reason: &'static str,
+}
+
+impl LoginFailure {
+    // Server-side errors are recorded as internal, whichever step raised them
+    pub fn new(reason: &'static str, error: AppError) -> Self {
+        let reason = match error {
+            AppError::Internal(_) | AppError::Database(_) => FAILURE_INTERNAL,
+            _ => reason,
+        };
+
+        Self { error, reason }
+    }
+}
+
+// Page size limits for the login history
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+// Longest identifier kept for a failed attempt
+const MAX_IDENTIFIER_LENGTH: usize = 255;
+
+// Longest description for an allowlist entry
+const MAX_DESCRIPTION_LENGTH: usize = 255;
+
+// What earlier successful sign-ins say about this client
+#[derive(FromRow)]
+struct KnownClient {
+    previous_logins: i64,
+    known_device: bool,
+    known_range: bool,
+}
+
+// Record a successful sign-in and alert the user if it came from somewhere new
+pub async fn record_successful_login(
+    pool: &PgPool,
+    user: &User,
+    client: &ClientInfo,
+    factor: &str,
+) -> Result<(), AppError> {
+    let ip_range = client.ip_range();
+
+    // Compare against earlier sign-ins before this one is added
+    let known = sqlx::query_as::<_, KnownClient>(
+        r#"
+        SELECT
+            COUNT(*) AS previous_logins,
+            COALESCE(bool_or(user_agent IS NOT DISTINCT FROM $2), false) AS known_device,
+            COALESCE(bool_or(ip_range IS NOT DISTINCT FROM $3), false) AS known_range
+        FROM login_events
+        WHERE user_id = $1 AND success
+        "#,
+    )
+    .bind(user.id)
+    .bind(&client.user_agent)
+    .bind(&ip_range)
+    .fetch_one(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO login_events (user_id, identifier, success, ip, ip_range, user_agent, factor)
+        VALUES ($1, $2, true, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(user.id)
+    .bind(&user.username)
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .bind(&ip_range)
+    .bind(&client.user_agent)
+    .bind(factor)
+    .execute(pool)
+    .await?;
+
+    // The very first sign-in has nothing to compare with
+    if known.previous_logins > 0 && (!known.known_device || !known.known_range) {
+        send_new_device_alert(user, client).await;
+    }
+
+    Ok(())
+}
+
+// Record a failed sign-in. Failures here are logged rather than returned so the
+// caller still sees the original error.
+pub async fn record_failed_login(pool: &PgPool, identifier: &str, client: &ClientInfo, reason: &str) {
+    let identifier: String = normalize_identifier(identifier)
+        .chars()
+        .take(MAX_IDENTIFIER_LENGTH)
+        .collect();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO login_events (user_id, identifier, success, ip, ip_range, user_agent, failure_reason)
+        VALUES (
+            (SELECT id FROM users WHERE lower(username) = lower($1) OR lower(email) = lower($1) LIMIT 1),
+            $1, false, $2, $3, $4, $5
+        )
+        "#,
+    )
+    .bind(&identifier)
+    .bind(client.ip.map(|ip| ip.to_string()))
+    .bind(client.ip_range())
+    .bind(&client.user_agent)
+    .bind(reason)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to record login attempt: {}", e);
+    }
+}
+
+// Tell the user about a sign-in from a device or network not seen before
+async fn send_new_device_alert(user: &User, client: &ClientInfo) {
+    let ip = client.ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+    let device = client.user_agent.as_deref().unwrap_or("unknown");
+
+    send_best_effort(Email {
+        to: user.email.clone(),
+        subject: "New sign-in to your account".to_string(),
+        body: format!(
+            "Hello {},\n\nYour account was just signed in to from a new device or location.\n\n\
+             IP address: {}\nDevice: {}\n\n\
+             If this was you, no action is needed. Otherwise change your password \
+             and review your login history.\n",
+            user.username, ip, device
+        ),
+    })
+    .await;
+}
+
+// List the current user's sign-in attempts, newest first
+pub async fn get_login_history(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Query(query): Query<LoginHistoryQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let events = sqlx::query_as::<_, LoginEvent>(
+        r#"
+        SELECT id, success, ip, user_agent, factor, failure_reason, created_at
+        FROM login_events
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(auth_user.user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(events)))
+}
+
+// List every allowlist entry on the current user's account, including admin policy
+pub async fn get_ip_allowlist(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let entries = list_allowlist_entries(&pool, auth_user.user_id).await?;
+
+    Ok((StatusCode::OK, Json(entries)))
+}
+
+// Add a range to the current user's allowlist
+pub async fn add_ip_allowlist_entry(
+    auth_user: AuthUser,
+    client: ClientInfo,
+    State(pool): State<PgPool>,
+    Json(payload): Json<ChangeIpAllowlist>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    reauthenticate(&pool, &user, &payload.proof).await?;
+
+    let net = parse_cidr(&payload.entry.cidr)?;
+
+    // The first entry must cover the address making the change
+    let mut allowlist = IpAllowlist::load(&pool, user.id).await?;
+    allowlist.user_ranges.push(net);
+    reject_lockout(&allowlist, &client)?;
+
+    let entry = insert_allowlist_entry(&pool, user.id, MANAGED_BY_USER, user.id, &payload.entry, net).await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+// Remove a range from the current user's allowlist
+pub async fn remove_ip_allowlist_entry(
+    auth_user: AuthUser,
+    client: ClientInfo,
+    State(pool): State<PgPool>,
+    Path(entry_id): Path<Uuid>,
+    Json(proof): Json<Reauthenticate>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth_user.user_id)
+        .fetch_one(&pool)
+        .await?;
+
+    // Admin policy entries are not the user's to remove
+    let entry = sqlx::query_as::<_, IpAllowlistEntry>(
+        "SELECT * FROM ip_allowlist_entries WHERE id = $1 AND user_id = $2 AND managed_by = $3",
+    )
+    .bind(entry_id)
+    .bind(user.id)
+    .bind(MANAGED_BY_USER)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Allowlist entry not found".to_string()))?;
+
+    reauthenticate(&pool, &user, &proof).await?;
+
+    let net = parse_cidr(&entry.cidr)?;
+    let mut allowlist = IpAllowlist::load(&pool, user.id).await?;
+    if let Some(position) = allowlist.user_ranges.iter().position(|range| *range == net) {
+        allowlist.user_ranges.remove(position);
+    }
+    reject_lockout(&allowlist, &client)?;
+
+    sqlx::query("DELETE FROM ip_allowlist_entries WHERE id = $1")
+        .bind(entry.id)
+        .execute(&pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Users may not change their allowlist in a way that shuts out their current address
+fn reject_lockout(allowlist: &IpAllowlist, client: &ClientInfo) -> Result<(), AppError> {
+    if !allowlist.allows(client.ip) {
+        return Err(AppError::BadRequest(
+            "This change would block the address you are connecting from".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// All allowlist entries for an account, admin policy first
+pub async fn list_allowlist_entries(pool: &PgPool, user_id: Uuid) -> Result<Vec<IpAllowlistEntry>, AppError> {
+    let entries = sqlx::query_as::<_, IpAllowlistEntry>(
+        r#"
+        SELECT * FROM ip_allowlist_entries
+        WHERE user_id = $1
+        ORDER BY managed_by, created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+// Store an allowlist entry in its canonical form
+pub async fn insert_allowlist_entry(
+    pool: &PgPool,
+    user_id: Uuid,
+    managed_by: &str,
+    created_by: Uuid,
+    entry: &CreateIpAllowlistEntry,
+    net: IpNet,
+) -> Result<IpAllowlistEntry, AppError> {
+    let description = entry.description.as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    if description.is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_LENGTH) {
+        return Err(AppError::BadRequest(format!(
+            "Description must be at most {} characters long",
+            MAX_DESCRIPTION_LENGTH
+        )));
+    }
+
+    sqlx::query_as::<_, IpAllowlistEntry>(
+        r#"
+        INSERT INTO ip_allowlist_entries (user_id, cidr, description, managed_by, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, managed_by, cidr) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(net.to_string())
+    .bind(description)
+    .bind(managed_by)
+    .bind(created_by)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict("Range is already on the allowlist".to_string()))
+}
+
+// List the devices that may skip TOTP on the current account
+pub async fn get_trusted_devices(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let devices = sqlx::query_as::<_, TrustedDevice>(
+        r#"
+        SELECT id, name, ip, created_at, last_used_at, expires_at
+        FROM trusted_devices
+        WHERE user_id = $1 AND expires_at > now()
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(auth_user.user_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(devices)))
+}
+
+// Stop trusting one device
+pub async fn revoke_trusted_device(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+    Path(device_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query("DELETE FROM trusted_devices WHERE id = $1 AND user_id = $2")
+        .bind(device_id)
+        .bind(auth_user.user_id)
+        .execute(&pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Trusted device not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Stop trusting every device
+pub async fn revoke_all_trusted_devices(
+    auth_user: AuthUser,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    let revoked = revoke_trusted_devices(&pool, auth_user.user_id).await?;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "revoked": revoked }))))
+}
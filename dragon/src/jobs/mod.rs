@@ -0,0 +1,115 @@
+use sqlx::PgPool;
+use std::{env, time::Duration};
+
+use crate::{errors::AppError, handlers::credential::password_history_depth};
+
+// How often background maintenance runs
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Days trashed items are kept when TRASH_RETENTION_DAYS is not set
+const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+
+// Hard-delete accounts whose deletion grace period has passed.
+// Categories, credentials and tokens go with them through ON DELETE CASCADE;
+// only a minimal record of the deletion is kept.
+pub async fn purge_deleted_accounts(pool: &PgPool) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO account_deletions (user_id, requested_at)
+        SELECT id, deletion_requested_at FROM users
+        WHERE deletion_scheduled_for IS NOT NULL AND deletion_scheduled_for <= now()
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let result = sqlx::query(
+        "DELETE FROM users WHERE deletion_scheduled_for IS NOT NULL AND deletion_scheduled_for <= now()",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected())
+}
+
+// Drop trashed categories and credentials older than the retention period
+pub async fn purge_trash(pool: &PgPool) -> Result<u64, AppError> {
+    let retention_days = env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+
+    let result = sqlx::query("DELETE FROM trash_items WHERE deleted_at <= now() - make_interval(days => $1)")
+        .bind(retention_days)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Drop previous passwords beyond the configured depth, such as those left over
+// after PASSWORD_HISTORY_DEPTH was lowered
+pub async fn trim_password_history(pool: &PgPool) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM password_history h
+        USING (
+            SELECT id, row_number() OVER (PARTITION BY credential_id ORDER BY replaced_at DESC, id DESC) AS rank
+            FROM password_history
+        ) ranked
+        WHERE h.id = ranked.id AND ranked.rank > $1
+        "#,
+    )
+    .bind(password_history_depth())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Forget logged-out tokens once they have expired and would be rejected anyway
+pub async fn purge_revoked_tokens(pool: &PgPool) -> Result<u64, AppError> {
+    let result = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= now()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Run the periodic maintenance jobs for the lifetime of the server
+pub fn spawn_background_jobs(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match purge_deleted_accounts(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Purged {} deleted accounts", count),
+                Err(e) => tracing::error!("Failed to purge deleted accounts: {}", e),
+            }
+
+            match purge_trash(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Purged {} expired trash items", count),
+                Err(e) => tracing::error!("Failed to purge the trash: {}", e),
+            }
+
+            match trim_password_history(&pool).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Trimmed {} previous passwords beyond the history depth", count),
+                Err(e) => tracing::error!("Failed to trim password history: {}", e),
+            }
+
+            if let Err(e) = purge_revoked_tokens(&pool).await {
+                tracing::error!("Failed to purge revoked tokens: {}", e);
+            }
+        }
+    });
+}
@@ -3,6 +3,10 @@ pub mod models;
 pub mod db;
 pub mod auth;
 pub mod handlers;
+pub mod jobs;
+pub mod mail;
 pub mod middleware;
 pub mod crypto;
+pub mod routes;
+pub mod vault;
 pub mod utils; 
\ No newline at end of file
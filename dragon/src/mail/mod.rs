@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::{env, path::PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::errors::AppError;
+
+// An outgoing plain-text email
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// Delivers account emails (verification, password reset, ...)
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+// Sends mail through an SMTP relay
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    // Build from SMTP_HOST, SMTP_PORT, SMTP_USERNAME and SMTP_PASSWORD
+    pub fn from_env(from: Mailbox) -> Result<Self, AppError> {
+        let host = env::var("SMTP_HOST")
+            .map_err(|_| AppError::Internal("SMTP_HOST must be set".to_string()))?;
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+            .map_err(|e| AppError::Internal(format!("Invalid SMTP relay: {}", e)))?;
+
+        if let Ok(port) = env::var("SMTP_PORT") {
+            let port = port.parse::<u16>()
+                .map_err(|_| AppError::Internal("SMTP_PORT must be a port number".to_string()))?;
+            builder = builder.port(port);
+        }
+
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self { transport: builder.build(), from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let to = email.to.parse::<Mailbox>()
+            .map_err(|e| AppError::BadRequest(format!("Invalid email address: {}", e)))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .body(email.body)
+            .map_err(|e| AppError::Internal(format!("Failed to build email: {}", e)))?;
+
+        self.transport.send(message).await
+            .map_err(|e| AppError::Internal(format!("Failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+// Appends each email to a local file, for development and tests
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let entry = format!(
+            "To: {}\nSubject: {}\n\n{}\n\n----\n",
+            email.to, email.subject, email.body
+        );
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to open mail file: {}", e)))?;
+
+        file.write_all(entry.as_bytes()).await
+            .map_err(|e| AppError::Internal(format!("Failed to write mail file: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+// Notes each email in the application log without sending it, for development only.
+// The body carries live tokens, so only the recipient and subject are logged.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!(to = %email.to, subject = %email.subject, "Email not sent (MAIL_BACKEND=log)");
+        Ok(())
+    }
+}
+
+// Build the mailer selected by MAIL_BACKEND ("smtp", "file" or "log"). There is no
+// default, so a deployment that forgot to configure mail fails instead of dropping it.
+pub fn mailer_from_env() -> Result<Box<dyn Mailer>, AppError> {
+    let backend = env::var("MAIL_BACKEND")
+        .map_err(|_| AppError::Internal("MAIL_BACKEND must be set".to_string()))?;
+
+    match backend.as_str() {
+        "log" => Ok(Box::new(LogMailer)),
+        "file" => {
+            let path = env::var("MAIL_FILE_PATH").unwrap_or_else(|_| "mail.log".to_string());
+            Ok(Box::new(FileMailer::new(path)))
+        }
+        "smtp" => {
+            let from = env::var("MAIL_FROM")
+                .unwrap_or_else(|_| "DragonFruit <no-reply@localhost>".to_string())
+                .parse::<Mailbox>()
+                .map_err(|e| AppError::Internal(format!("Invalid MAIL_FROM: {}", e)))?;
+            Ok(Box::new(SmtpMailer::from_env(from)?))
+        }
+        other => Err(AppError::Internal(format!("Unknown MAIL_BACKEND: {}", other))),
+    }
+}
+
+// Base URL of the web client, used to build links in emails
+pub fn frontend_url(path: &str) -> String {
+    let origin = env::var("FRONTEND_ORIGIN")
+        .unwrap_or_else(|_| "http://localhost:5173".to_string());
+    format!("{}{}", origin.trim_end_matches('/'), path)
+}
+
+// Send an email, logging failures instead of failing the request
+pub async fn send_best_effort(email: Email) {
+    let result = match mailer_from_env() {
+        Ok(mailer) => mailer.send(email).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to deliver email: {}", e);
+    }
+}
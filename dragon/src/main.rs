@@ -9,27 +9,25 @@ mod errors;
 #[allow(dead_code)]
 mod handlers;
 #[allow(dead_code)]
+mod jobs;
+#[allow(dead_code)]
+mod mail;
+#[allow(dead_code)]
 mod middleware;
 #[allow(dead_code)]
 mod models;
 #[allow(dead_code)]
 mod routes;
+#[allow(dead_code)]
+mod utils;
+#[allow(dead_code)]
+mod vault;
 
 use std::net::SocketAddr;
-use axum::{
-    routing::{get, post, put},
-    Router,
-};
-use axum::middleware::from_fn_with_state;
-use tower_http::cors::{CorsLayer, Any};
 
 use crate::{
-    db::create_pool,
-    handlers::{
-        // Auth handlers
-        register, login, generate_totp_for_user, enable_totp, get_profile, update_profile,
-    },
-    middleware::auth::require_auth,
+    db::{create_pool, missing_columns, run_migrations},
+    routes::create_router,
 };
 
 #[tokio::main]
@@ -43,43 +41,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create database connection pool
     let pool = create_pool().await;
     
-    // Set up CORS middleware
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    // Bring the schema up to date, then make sure it has everything the code expects
+    run_migrations(&pool).await?;
+    
+    let missing = missing_columns(&pool).await?;
+    if !missing.is_empty() {
+        return Err(format!("Database schema is missing columns: {}", missing.join(", ")).into());
+    }
+    
+    // Refuse to start without a working mail setup or with a bad grace period
+    mail::mailer_from_env().map_err(|e| e.to_string())?;
+    handlers::account::deletion_grace_days().map_err(|e| e.to_string())?;
     
-    // Define public routes (no auth required)
-    let public_routes = Router::new()
-        .route("/auth/register", post(register))
-        .route("/auth/login", post(login))
-        .with_state(pool.clone());
+    // Start periodic maintenance (account purges)
+    jobs::spawn_background_jobs(pool.clone());
     
-    // Define protected routes (auth required)
-    let protected_routes = Router::new()
-        .route("/auth/profile", get(get_profile))
-        .route("/auth/profile", put(update_profile))
-        .route("/auth/totp/generate", post(generate_totp_for_user))
-        .route("/auth/totp/enable", post(enable_totp))
-        .with_state(pool.clone())
-        .route_layer(from_fn_with_state(pool.clone(), require_auth));
-        
-    // Build the application with middleware
-    let app = Router::new()
-        .nest("/api", 
-            Router::new()
-                .merge(public_routes)
-                .merge(protected_routes)
-        )
-        .layer(cors)
-        .fallback(|| async { "Dragon Fruit Password Manager API" });
+    // Every handler, mounted under /api
+    let app = create_router(pool);
     
     // Start the server
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     println!("Starting server on {}", addr);
     
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        // Connect info gives handlers the client address
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
     
     Ok(())
@@ -1,50 +1,138 @@
 use async_trait::async_trait;
 use axum::{
-    extract::FromRequestParts,
+    extract::{FromRef, FromRequestParts},
     http::{request::Parts, Request},
     middleware::Next,
     response::Response,
 };
+use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
 use crate::{
-    auth::jwt::validate_token,
+    auth::{
+        jwt::{validate_token, TokenSubject},
+        network::IpAllowlist,
+        policy::UnverifiedPolicy,
+        session::{check_csrf, session_from_cookie},
+    },
     errors::AppError,
+    middleware::client::ClientInfo,
+    models::user::ROLE_ADMIN,
 };
 
+// Routes still reachable while an admin-required TOTP enrolment is pending
+const TOTP_ENROLMENT_PATHS: &[&str] = &["/auth/totp/generate", "/auth/totp/enable", "/auth/profile"];
+
 // Extract user ID from JWT token
+#[derive(Clone)]
 pub struct AuthUser {
     pub user_id: Uuid,
+    pub role: String,
+    pub token: TokenSubject,
+}
+
+// Account state checked on every authenticated request
+#[derive(FromRow)]
+struct AccountState {
+    token_version: i32,
+    role: String,
+    disabled: bool,
+    email_verified: bool,
+    totp_enabled: bool,
+    totp_required: bool,
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
+    PgPool: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the token from the Authorization header
-        let auth_header = parts
-            .headers
-            .get("Authorization")
-            .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".to_string()))?
-            .to_str()
-            .map_err(|_| AppError::Unauthorized("Invalid Authorization header".to_string()))?;
-
-        // Check if it's a Bearer token
-        if !auth_header.starts_with("Bearer ") {
-            return Err(AppError::Unauthorized("Invalid Authorization scheme".to_string()));
-        }
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Prefer the Authorization header; the web client may use the session cookie instead
+        let token = match parts.headers.get("Authorization") {
+            Some(auth_header) => {
+                let auth_header = auth_header
+                    .to_str()
+                    .map_err(|_| AppError::Unauthorized("Invalid Authorization header".to_string()))?;
+
+                // Check if it's a Bearer token
+                if !auth_header.starts_with("Bearer ") {
+                    return Err(AppError::Unauthorized("Invalid Authorization scheme".to_string()));
+                }
+
+                // Extract the token
+                auth_header[7..].to_string()
+            }
+            None => {
+                let token = session_from_cookie(&parts.headers)
+                    .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".to_string()))?;
 
-        // Extract the token
-        let token = &auth_header[7..];
+                // Browsers attach cookies to cross-site requests too
+                check_csrf(&parts.method, &parts.headers, &token)?;
+                token
+            }
+        };
 
         // Validate the token and extract the user ID
-        let user_id = validate_token(token)?;
+        let subject = validate_token(&token)?;
+
+        // Tokens are revoked by bumping the user's token version
+        let pool = PgPool::from_ref(state);
+        let account = sqlx::query_as::<_, AccountState>(
+            r#"
+            SELECT token_version, role, disabled, email_verified, totp_enabled, totp_required
+            FROM users WHERE id = $1
+            "#,
+        )
+        .bind(subject.user_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Account not found".to_string()))?;
+
+        if account.token_version != subject.token_version {
+            return Err(AppError::Unauthorized("Token revoked".to_string()));
+        }
+
+        // Single tokens are revoked by logging out
+        if let Some(token_id) = subject.token_id {
+            let revoked = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE token_id = $1)")
+                .bind(token_id)
+                .fetch_one(&pool)
+                .await?;
+
+            if revoked {
+                return Err(AppError::Unauthorized("Token revoked".to_string()));
+            }
+        }
+
+        if account.disabled {
+            return Err(AppError::Unauthorized("Account disabled".to_string()));
+        }
+
+        if !account.email_verified && UnverifiedPolicy::from_env() == UnverifiedPolicy::Block {
+            return Err(AppError::Unauthorized("Email address not verified".to_string()));
+        }
+
+        // A token is only good from addresses on the account's allowlists
+        let Ok(client) = ClientInfo::from_request_parts(parts, state).await;
+        IpAllowlist::load(&pool, subject.user_id).await?.check(client.ip)?;
 
-        Ok(AuthUser { user_id })
+        // Until the user enrols, only the TOTP setup routes are open
+        if account.totp_required && !account.totp_enabled {
+            let path = parts.uri.path();
+            if !TOTP_ENROLMENT_PATHS.iter().any(|allowed| path.ends_with(allowed)) {
+                return Err(AppError::Forbidden("TOTP enrolment required".to_string()));
+            }
+        }
+
+        Ok(AuthUser {
+            user_id: subject.user_id,
+            role: account.role,
+            token: subject,
+        })
     }
 }
 
@@ -59,4 +147,40 @@ pub async fn require_auth<B>(
         Ok(_) => Ok(next.run(req).await),
         Err(e) => Err(e),
     }
-} 
\ No newline at end of file
+}
+
+// Authenticated user with the admin role
+pub struct AdminUser {
+    pub user_id: Uuid,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth_user = AuthUser::from_request_parts(parts, state).await?;
+
+        if auth_user.role != ROLE_ADMIN {
+            return Err(AppError::Forbidden("Administrator access required".to_string()));
+        }
+
+        Ok(AdminUser { user_id: auth_user.user_id })
+    }
+}
+
+// Middleware to require the admin role
+pub async fn require_admin<B>(
+    admin_user: Result<AdminUser, AppError>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, AppError> {
+    match admin_user {
+        Ok(_) => Ok(next.run(req).await),
+        Err(e) => Err(e),
+    }
+}
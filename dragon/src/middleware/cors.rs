@@ -1,5 +1,9 @@
 use std::env;
-use tower_http::cors::{CorsLayer, Any};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::HeaderName;
+use tower_http::cors::CorsLayer;
+
+use crate::auth::session::CSRF_HEADER;
 
 pub fn cors_layer() -> CorsLayer {
     let frontend_origin = env::var("FRONTEND_ORIGIN")
@@ -14,6 +18,7 @@ pub fn cors_layer() -> CorsLayer {
             axum::http::Method::DELETE,
             axum::http::Method::OPTIONS,
         ])
-        .allow_headers(Any)
+        // Credentials rule out a wildcard, so the headers are listed
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE, HeaderName::from_static(CSRF_HEADER)])
         .allow_credentials(true)
 } 
\ No newline at end of file
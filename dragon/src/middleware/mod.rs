@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod client;
 pub mod cors;
 
-pub use auth::*; 
\ No newline at end of file
+pub use auth::*;
\ No newline at end of file
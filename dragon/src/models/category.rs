@@ -3,6 +3,8 @@ use sqlx::FromRow;
 use uuid::Uuid;
 use time::OffsetDateTime;
 
+use crate::models::credential::ItemType;
+
 // Simplified time serializer without external dependency
 mod datetime_serializer {
     use serde::{Deserialize, Deserializer, Serializer, Serialize};
@@ -24,6 +26,7 @@ mod datetime_serializer {
             .map_err(serde::de::Error::custom)
     }
     
+    #[allow(dead_code)]
     pub mod option {
         use super::*;
         
@@ -58,22 +61,66 @@ pub struct Category {
     pub user_id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub position: i32,         // Order among siblings, lowest first
+    pub color: Option<String>, // "#rrggbb"
+    pub icon: Option<String>,
+    pub collapsed: bool,       // Shown collapsed by default
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
     #[serde(with = "datetime_serializer")]
     pub updated_at: OffsetDateTime,
 }
 
+// A category with the number of credentials in it and in its whole subtree
+#[derive(Debug, Clone, FromRow)]
+pub struct CategoryWithCounts {
+    #[sqlx(flatten)]
+    pub category: Category,
+    pub credential_count: i64,
+    pub subtree_credential_count: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateCategory {
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>, // Top level when omitted
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub collapsed: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCategory {
     pub name: Option<String>,
     pub description: Option<String>,
+    // Omitted leaves the category where it is; null moves it to the top level
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub parent_id: Option<Option<Uuid>>,
+    // Omitted leaves these alone; null clears them
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub icon: Option<Option<String>>,
+    pub collapsed: Option<bool>,
+}
+
+// The new order of the subcategories of `parent_id` (the top level when null)
+#[derive(Debug, Deserialize)]
+pub struct ReorderCategories {
+    pub parent_id: Option<Uuid>,
+    pub order: Vec<Uuid>, // Every sibling exactly once
+}
+
+// Tell an explicit null apart from a missing field
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -92,20 +139,102 @@ pub struct CategoryUpdate {
 pub struct CategoryResponse {
     pub id: Uuid,
     pub name: String,
+    pub description: Option<String>,
     pub parent_id: Option<Uuid>,
+    pub position: i32,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub collapsed: bool,
+    pub credential_count: i64,
+    pub subtree_credential_count: i64,
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer")]
+    pub updated_at: OffsetDateTime,
     pub children: Option<Vec<CategoryResponse>>,
 }
 
-impl From<Category> for CategoryResponse {
-    fn from(category: Category) -> Self {
+impl From<CategoryWithCounts> for CategoryResponse {
+    fn from(row: CategoryWithCounts) -> Self {
+        let category = row.category;
         Self {
             id: category.id,
             name: category.name,
-            parent_id: None,
+            description: category.description,
+            parent_id: category.parent_id,
+            position: category.position,
+            color: category.color,
+            icon: category.icon,
+            collapsed: category.collapsed,
+            credential_count: row.credential_count,
+            subtree_credential_count: row.subtree_credential_count,
             created_at: category.created_at,
+            updated_at: category.updated_at,
             children: None,
         }
     }
-} 
\ No newline at end of file
+}
+
+// Only for a category that cannot hold credentials yet, such as one just created
+impl From<Category> for CategoryResponse {
+    fn from(category: Category) -> Self {
+        Self::from(CategoryWithCounts {
+            category,
+            credential_count: 0,
+            subtree_credential_count: 0,
+        })
+    }
+}
+
+// Top-level category that catches credentials whose category went away
+pub const UNFILED_CATEGORY: &str = "Unfiled";
+
+// What happens to a category's contents when it is deleted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteStrategy {
+    #[default]
+    Reject,        // Only delete empty categories
+    MoveToParent,  // Subcategories and credentials move up one level
+    MoveToUnfiled, // Subcategories and credentials move into "Unfiled"
+    Trash,         // Delete the whole subtree, keeping a copy in the trash
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCategoryQuery {
+    #[serde(default)]
+    pub strategy: DeleteStrategy,
+}
+
+// What a deletion did
+#[derive(Debug, Serialize)]
+pub struct CategoryDeletion {
+    pub strategy: DeleteStrategy,
+    pub deleted_categories: Vec<Uuid>,
+    pub moved_to: Option<Uuid>, // Destination of moved contents; null means the top level
+    pub moved_categories: Vec<Uuid>,
+    pub moved_credentials: Vec<Uuid>,
+    pub trashed_credentials: Vec<Uuid>,
+}
+
+// A slash-separated path of category names, such as "Infra/AWS/prod"
+#[derive(Debug, Deserialize)]
+pub struct CategoryPathQuery {
+    pub path: String,
+    #[serde(default)]
+    pub recursive: bool, // Include credentials in subcategories
+    #[serde(rename = "type")]
+    pub item_type: Option<ItemType>, // Only credentials of this type
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCategoryPath {
+    pub path: String,
+}
+
+// The category at the end of a path and any categories created to reach it
+#[derive(Debug, Serialize)]
+pub struct CategoryPathCreation {
+    pub category: CategoryResponse,
+    pub created: Vec<CategoryResponse>,
+}
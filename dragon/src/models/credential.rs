@@ -24,6 +24,7 @@ mod datetime_serializer {
             .map_err(serde::de::Error::custom)
     }
     
+    #[allow(dead_code)]
     pub mod option {
         use super::*;
         
@@ -52,14 +53,133 @@ mod datetime_serializer {
     }
 }
 
+// Kinds of vault items. Logins use the username and password columns; every
+// other type keeps its fields in an encrypted JSON payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemType {
+    #[default]
+    Login,
+    SecureNote,
+    Card,
+    Identity,
+    ApiKey,
+    SshKey,
+    Database,
+}
+
+impl ItemType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::SecureNote => "secure_note",
+            Self::Card => "card",
+            Self::Identity => "identity",
+            Self::ApiKey => "api_key",
+            Self::SshKey => "ssh_key",
+            Self::Database => "database",
+        }
+    }
+}
+
+impl std::str::FromStr for ItemType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::Login, Self::SecureNote, Self::Card, Self::Identity, Self::ApiKey, Self::SshKey, Self::Database,
+        ]
+        .into_iter()
+        .find(|item_type| item_type.as_str() == s)
+        .ok_or_else(|| format!("Unknown item type: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecureNoteData {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CardData {
+    pub cardholder: String,
+    pub number: String,
+    pub expiry_month: u8,
+    pub expiry_year: u16, // Two-digit years are read as 20xx
+    pub cvv: Option<String>,
+    pub pin: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdentityData {
+    pub title: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub date_of_birth: Option<String>,
+    pub document_number: Option<String>, // Passport, licence or national id
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyData {
+    pub key: String,
+    pub secret: Option<String>,
+    pub endpoint: Option<String>,
+    pub expires_on: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SshKeyData {
+    pub private_key: Option<String>,
+    pub public_key: Option<String>, // Taken from an OpenSSH private key when omitted
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseData {
+    pub engine: String, // postgres, mysql, mssql, oracle, mongodb, redis, ...
+    pub host: String,
+    pub port: Option<u16>,
+    pub database: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub options: Option<String>, // Extra connection parameters
+}
+
+// The typed fields of a non-login item
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ItemData {
+    SecureNote(SecureNoteData),
+    Card(CardData),
+    Identity(IdentityData),
+    ApiKey(ApiKeyData),
+    SshKey(SshKeyData),
+    Database(DatabaseData),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Credential {
     pub id: Uuid,
     pub user_id: Uuid,
     pub category_id: Uuid,
+    pub item_type: String,
     pub name: String,
-    pub username: String,
-    pub password: String,
+    pub username: String, // Empty for non-login items
+    pub password: String, // Encrypted; empty for non-login items
+    pub payload: Option<String>, // Encrypted ItemData JSON for non-login items
+    pub metadata: serde_json::Value, // Non-secret summary, such as a card's last four digits
     pub website: Option<String>,
     pub notes: Option<String>,
     #[serde(with = "datetime_serializer")]
@@ -71,11 +191,18 @@ pub struct Credential {
 #[derive(Debug, Deserialize)]
 pub struct CreateCredential {
     pub category_id: Uuid,
+    #[serde(default)]
+    pub item_type: ItemType,
     pub name: String,
-    pub username: String,
-    pub password: String,
+    #[serde(default)]
+    pub username: String, // Logins only
+    #[serde(default)]
+    pub password: String, // Logins only
+    pub data: Option<serde_json::Value>, // The typed fields of any other item
     pub website: Option<String>,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<CustomFieldInput>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,18 +210,117 @@ pub struct UpdateCredential {
     pub name: Option<String>,
     pub username: Option<String>,
     pub password: Option<String>,
+    pub data: Option<serde_json::Value>, // Replaces all typed fields; the type cannot change
     pub website: Option<String>,
     pub notes: Option<String>,
     pub category_id: Option<Uuid>,
+    pub fields: Option<Vec<CustomFieldInput>>, // Replaces all custom fields, in the order given
+}
+
+// Narrow a credential list to one item type
+#[derive(Debug, Deserialize)]
+pub struct CredentialFilter {
+    #[serde(rename = "type")]
+    pub item_type: Option<ItemType>,
+}
+
+// Kinds of custom field. Hidden values are encrypted like passwords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Text,
+    Hidden,
+    Boolean,
+    Url,
+    Date,
+}
+
+impl FieldType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Hidden => "hidden",
+            Self::Boolean => "boolean",
+            Self::Url => "url",
+            Self::Date => "date",
+        }
+    }
+}
+
+impl std::str::FromStr for FieldType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Text, Self::Hidden, Self::Boolean, Self::Url, Self::Date]
+            .into_iter()
+            .find(|field_type| field_type.as_str() == s)
+            .ok_or_else(|| format!("Unknown field type: {}", s))
+    }
+}
+
+// A custom field of a credential, with its value stored as text
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CredentialField {
+    pub id: Uuid,
+    pub credential_id: Uuid,
+    pub position: i32,
+    pub label: String,
+    pub field_type: String,
+    pub value: String, // "true"/"false", YYYY-MM-DD, or encrypted for hidden fields
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer")]
+    pub updated_at: OffsetDateTime,
+}
+
+// A custom field sent on create or update. An existing field given by id keeps
+// its value when none is sent, so hidden values need not be revealed to edit a label.
+#[derive(Debug, Deserialize)]
+pub struct CustomFieldInput {
+    pub id: Option<Uuid>,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub value: Option<serde_json::Value>, // A boolean for boolean fields, otherwise a string
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomFieldResponse {
+    pub id: Uuid,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub value: Option<serde_json::Value>, // Null for hidden fields unless revealed
+}
+
+// A password a login had before it was replaced, encrypted like the current one
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PasswordHistoryEntry {
+    pub id: Uuid,
+    pub credential_id: Uuid,
+    pub password: String,
+    #[serde(with = "datetime_serializer")]
+    pub replaced_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordHistoryResponse {
+    pub id: Uuid,
+    pub password: Option<String>, // Null in lists; decrypted when revealed
+    #[serde(with = "datetime_serializer")]
+    pub replaced_at: OffsetDateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CredentialResponse {
     pub id: Uuid,
     pub category_id: Option<Uuid>,
+    pub item_type: String,
+    pub metadata: serde_json::Value,
     pub name: String,
     pub website: Option<String>,
     pub username: String,
+    pub fields: Vec<CustomFieldResponse>,
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
     #[serde(with = "datetime_serializer")]
@@ -105,10 +331,14 @@ pub struct CredentialResponse {
 pub struct CredentialWithPassword {
     pub id: Uuid,
     pub category_id: Option<Uuid>,
+    pub item_type: String,
+    pub metadata: serde_json::Value,
     pub name: String,
     pub website: Option<String>,
     pub username: String,
     pub password: String,
+    pub data: Option<serde_json::Value>, // Decrypted typed fields of non-login items
+    pub fields: Vec<CustomFieldResponse>, // Hidden values decrypted
     pub notes: Option<String>,
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
@@ -121,9 +351,12 @@ impl From<Credential> for CredentialResponse {
         Self {
             id: credential.id,
             category_id: Some(credential.category_id),
+            item_type: credential.item_type,
+            metadata: credential.metadata,
             name: credential.name,
             website: credential.website,
             username: credential.username,
+            fields: Vec::new(),
             created_at: credential.created_at,
             updated_at: credential.updated_at,
         }
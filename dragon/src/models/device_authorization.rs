@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+use crate::utils::time::datetime_serializer;
+
+// Values of device_authorizations.status
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_APPROVED: &str = "approved";
+pub const STATUS_DENIED: &str = "denied";
+pub const STATUS_ISSUED: &str = "issued"; // Tokens handed to the device
+
+// A device authorization request (RFC 8628) and the device it came from
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceAuthorization {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    pub device_code_hash: String,
+    #[serde(skip_serializing)]
+    pub user_code_hash: String,
+    pub client_id: String,
+    pub device_name: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub status: String,
+    pub user_id: Option<Uuid>, // Who approved or denied it
+    #[serde(skip_serializing)]
+    pub interval_secs: i32,
+    #[serde(skip_serializing)]
+    pub last_polled_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer::option")]
+    pub decided_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer")]
+    pub expires_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+// Device authorization request, sent form-encoded by the device
+#[derive(Debug, Deserialize)]
+pub struct DeviceCodeRequest {
+    pub client_id: String,
+    pub scope: Option<String>,
+    pub device_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+// Token request, sent form-encoded by the polling device
+#[derive(Debug, Deserialize)]
+pub struct DeviceTokenRequest {
+    pub grant_type: String,
+    pub device_code: String,
+    pub client_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserCodeQuery {
+    pub user_code: String,
+}
+
+// The signed-in user's answer to a request
+#[derive(Debug, Deserialize)]
+pub struct DeviceDecision {
+    pub user_code: String,
+    pub approve: bool,
+}
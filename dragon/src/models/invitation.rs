@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+use crate::utils::time::datetime_serializer;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Invitation {
+    pub id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub email: Option<String>,
+    pub created_by: Uuid,
+    pub used_by: Option<Uuid>,
+    #[serde(with = "datetime_serializer")]
+    pub expires_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer::option")]
+    pub used_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitation {
+    pub email: Option<String>, // Restrict the invitation to one address and email it there
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub email: Option<String>,
+    #[serde(with = "datetime_serializer")]
+    pub expires_at: OffsetDateTime,
+    pub used: bool,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+// Returned once, when the invitation is created
+#[derive(Debug, Serialize)]
+pub struct CreatedInvitation {
+    pub invitation: InvitationResponse,
+    pub token: String,
+    pub link: String,
+}
+
+impl From<Invitation> for InvitationResponse {
+    fn from(invitation: Invitation) -> Self {
+        Self {
+            id: invitation.id,
+            email: invitation.email,
+            expires_at: invitation.expires_at,
+            used: invitation.used_at.is_some(),
+            created_at: invitation.created_at,
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub mod user;
 pub mod category;
-pub mod credential; 
\ No newline at end of file
+pub mod credential;
+pub mod invitation;
+pub mod device_authorization;
+pub mod trash;
+pub mod revision;
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+use crate::{models::credential::FieldType, utils::time::datetime_serializer};
+
+// An immutable record of an item's state after one change. Version 1 of an item is
+// the state before its first recorded change. Secret columns keep the encrypted
+// form they have in the main tables.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Revision {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub item_type: String, // ITEM_CATEGORY or ITEM_CREDENTIAL
+    pub item_id: Uuid,
+    pub version: i32,
+    pub changed_by: Uuid,
+    pub changed_fields: Vec<String>, // Empty for the first version
+    pub data: serde_json::Value,
+    pub rollback_of: Option<Uuid>, // The revision a rollback restored
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionResponse {
+    pub id: Uuid,
+    pub version: i32,
+    pub changed_by: Uuid,
+    pub changed_fields: Vec<String>,
+    pub rollback_of: Option<Uuid>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+impl From<Revision> for RevisionResponse {
+    fn from(revision: Revision) -> Self {
+        Self {
+            id: revision.id,
+            version: revision.version,
+            changed_by: revision.changed_by,
+            changed_fields: revision.changed_fields,
+            rollback_of: revision.rollback_of,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+// Compare two revisions, or one revision with the item as it is now when `to` is omitted
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: Uuid,
+    pub to: Option<Uuid>,
+}
+
+// One field that differs between two revisions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+    pub secret: bool, // Encrypted values are never shown, only reported as changed
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionDiff {
+    pub from: Uuid,
+    pub to: Option<Uuid>, // None for the current state
+    pub changes: Vec<FieldChange>,
+}
+
+// The fields of a credential revision, as stored in its data
+#[derive(Debug, Deserialize)]
+pub struct CredentialSnapshot {
+    pub category_id: Uuid,
+    pub name: String,
+    pub username: String,
+    pub payload: Option<String>,
+    pub metadata: serde_json::Value,
+    pub website: Option<String>,
+    pub notes: Option<String>,
+    pub fields: Vec<SnapshotField>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotField {
+    pub id: Uuid,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub value: String,
+}
+
+// The fields of a category revision, as stored in its data
+#[derive(Debug, Deserialize)]
+pub struct CategorySnapshot {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub collapsed: bool,
+}
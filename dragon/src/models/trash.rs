@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use time::OffsetDateTime;
+
+use crate::utils::time::datetime_serializer;
+
+// Kinds of trashed items
+pub const ITEM_CATEGORY: &str = "category";
+pub const ITEM_CREDENTIAL: &str = "credential";
+
+// A deleted category or credential; the full row is kept in the database
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrashItem {
+    pub id: Uuid,
+    pub item_type: String,
+    pub item_id: Uuid,
+    pub name: Option<String>,
+    #[serde(with = "datetime_serializer")]
+    pub deleted_at: OffsetDateTime,
+}
+
+// What restoring an item from the trash brought back
+#[derive(Debug, Serialize)]
+pub struct TrashRestore {
+    pub restored_categories: Vec<Uuid>,
+    pub restored_credentials: Vec<Uuid>,
+}
@@ -52,15 +52,31 @@ mod datetime_serializer {
     }
 }
 
+// Values of users.role
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub email: String,
+    pub pending_email: Option<String>, // Awaiting confirmation from the new address
     #[serde(skip_serializing)]
     pub password_hash: String,
+    pub auth_source: String, // "local" or "ldap"
+    pub role: String,        // ROLE_USER or ROLE_ADMIN
+    pub disabled: bool,
     pub totp_secret: Option<String>,
     pub totp_enabled: bool,
+    pub totp_required: bool,
+    pub email_otp_enabled: bool, // Emailed codes as a second factor
+    pub password_reset_required: bool,
+    pub email_verified: bool,
+    #[serde(skip_serializing)]
+    pub token_version: i32,
+    #[serde(with = "datetime_serializer::option")]
+    pub deletion_scheduled_for: Option<OffsetDateTime>,
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
     #[serde(with = "datetime_serializer")]
@@ -68,11 +84,19 @@ pub struct User {
     pub last_login: Option<OffsetDateTime>,
 }
 
+// Proof of identity for sensitive account changes
+#[derive(Debug, Deserialize)]
+pub struct Reauthenticate {
+    pub password: String,
+    pub totp_code: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateUser {
     pub username: String,
     pub email: String,
     pub password: String,
+    pub invite_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,9 +109,44 @@ pub struct UpdateUser {
 
 #[derive(Debug, Deserialize)]
 pub struct LoginUser {
-    pub username: String,
+    #[serde(alias = "username", alias = "email")]
+    pub identifier: String, // Username or email address
     pub password: String,
     pub totp_code: Option<String>,
+    pub email_code: Option<String>,   // Emailed one-time code
+    pub device_token: Option<String>, // Skips the second factor on a trusted device
+    #[serde(default)]
+    pub trust_device: bool, // Trust this device after a second-factor login
+    pub device_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPasswordReset {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmail {
+    pub new_email: String,
+    #[serde(flatten)]
+    pub proof: Reauthenticate,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePassword {
+    pub current_password: String,
+    pub new_password: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -95,13 +154,20 @@ pub struct UserResponse {
     pub id: Uuid,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>,
+    pub role: String,
     pub totp_enabled: bool,
+    pub email_otp_enabled: bool,
+    pub email_verified: bool,
     #[serde(with = "datetime_serializer")]
     pub created_at: OffsetDateTime,
     #[serde(with = "datetime_serializer")]
     pub updated_at: OffsetDateTime,
     #[serde(with = "datetime_serializer::option", skip_serializing_if = "Option::is_none")]
     pub last_login: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer::option", skip_serializing_if = "Option::is_none")]
+    pub deletion_scheduled_for: Option<OffsetDateTime>,
 }
 
 impl From<User> for UserResponse {
@@ -110,14 +176,148 @@ impl From<User> for UserResponse {
             id: user.id,
             username: user.username,
             email: user.email,
+            pending_email: user.pending_email,
+            role: user.role,
             totp_enabled: user.totp_enabled,
+            email_otp_enabled: user.email_otp_enabled,
+            email_verified: user.email_verified,
             created_at: user.created_at,
             updated_at: user.updated_at,
             last_login: user.last_login,
+            deletion_scheduled_for: user.deletion_scheduled_for,
         }
     }
 }
 
+// Search and paging for the admin user list
+#[derive(Debug, Deserialize)]
+pub struct UserSearch {
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRole {
+    pub role: String,
+}
+
+// Full account state as shown to administrators
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: String,
+    pub auth_source: String,
+    pub disabled: bool,
+    pub email_verified: bool,
+    pub totp_enabled: bool,
+    pub totp_required: bool,
+    pub email_otp_enabled: bool,
+    pub password_reset_required: bool,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer::option", skip_serializing_if = "Option::is_none")]
+    pub last_login: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer::option", skip_serializing_if = "Option::is_none")]
+    pub deletion_scheduled_for: Option<OffsetDateTime>,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            role: user.role,
+            auth_source: user.auth_source,
+            disabled: user.disabled,
+            email_verified: user.email_verified,
+            totp_enabled: user.totp_enabled,
+            totp_required: user.totp_required,
+            email_otp_enabled: user.email_otp_enabled,
+            password_reset_required: user.password_reset_required,
+            created_at: user.created_at,
+            last_login: user.last_login,
+            deletion_scheduled_for: user.deletion_scheduled_for,
+        }
+    }
+}
+
+// An entry in the admin audit log
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AdminAction {
+    pub id: Uuid,
+    pub admin_id: Option<Uuid>,
+    pub target_user_id: Option<Uuid>,
+    pub action: String,
+    pub details: Option<String>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+// One sign-in attempt from the login history
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct LoginEvent {
+    pub id: Uuid,
+    pub success: bool,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub factor: Option<String>,
+    pub failure_reason: Option<String>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginHistoryQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// A CIDR range on an account's allowlist
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct IpAllowlistEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub cidr: String,
+    pub description: Option<String>,
+    pub managed_by: String,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIpAllowlistEntry {
+    pub cidr: String,
+    pub description: Option<String>,
+}
+
+// Users must prove who they are before changing their own allowlist
+#[derive(Debug, Deserialize)]
+pub struct ChangeIpAllowlist {
+    #[serde(flatten)]
+    pub entry: CreateIpAllowlistEntry,
+    #[serde(flatten)]
+    pub proof: Reauthenticate,
+}
+
+// A device that may sign in without a TOTP code
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TrustedDevice {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    #[serde(with = "datetime_serializer")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "datetime_serializer::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "datetime_serializer")]
+    pub expires_at: OffsetDateTime,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenPair {
     pub access_token: String,
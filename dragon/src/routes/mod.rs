@@ -1,9 +1,10 @@
 use crate::db::DbPool;
 use crate::handlers;
-use crate::middleware::require_auth;
+use crate::middleware::{require_admin, require_auth};
+use crate::middleware::cors::cors_layer;
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
@@ -11,42 +12,121 @@ use axum::{
 pub fn create_router(db_pool: DbPool) -> Router {
     // Public routes (no authentication required)
     let public_routes = Router::new()
-        .route("/register", post(handlers::register))
-        .route("/login", post(handlers::login));
-    
+        .route("/auth/register", post(handlers::register))
+        .route("/auth/login", post(handlers::login))
+        .route("/auth/logout", post(handlers::logout))
+        .route("/auth/verify-email", post(handlers::verify_email))
+        .route("/auth/verify-email/resend", post(handlers::resend_verification_email))
+        .route("/auth/password-reset/request", post(handlers::request_password_reset))
+        .route("/auth/password-reset/confirm", post(handlers::confirm_password_reset))
+        .route("/auth/email/confirm", post(handlers::confirm_email_change))
+        .route("/auth/email/cancel", post(handlers::cancel_email_change))
+        .route("/oauth/device/code", post(handlers::request_device_code))
+        .route("/oauth/token", post(handlers::exchange_device_code));
+
     // Protected routes (authentication required)
     let protected_routes = Router::new()
-        // User routes
-        .route("/me", get(handlers::get_profile))
-        .route("/totp/setup", post(handlers::generate_totp_for_user))
-        .route("/totp/verify", post(handlers::enable_totp))
-        // Disabled for now, needs implementation
-        // .route("/totp/disable", post(handlers::disable_totp))
-        
+        // Account routes
+        .route("/auth/profile", get(handlers::get_profile).put(handlers::update_profile))
+        .route("/auth/csrf", get(handlers::get_csrf_token))
+        .route("/auth/password", post(handlers::change_password))
+        .route("/auth/email", post(handlers::request_email_change))
+        .route("/auth/account/delete", post(handlers::request_account_deletion))
+        .route("/auth/totp/generate", post(handlers::generate_totp_for_user))
+        .route("/auth/totp/enable", post(handlers::enable_totp))
+        .route("/auth/email-otp/enable", post(handlers::enable_email_otp))
+        .route("/auth/email-otp/disable", post(handlers::disable_email_otp))
+
+        // Security routes
+        .route("/auth/login-history", get(handlers::get_login_history))
+        .route("/auth/ip-allowlist", get(handlers::get_ip_allowlist).post(handlers::add_ip_allowlist_entry))
+        .route("/auth/ip-allowlist/:id", delete(handlers::remove_ip_allowlist_entry))
+        .route("/auth/devices", get(handlers::get_trusted_devices).delete(handlers::revoke_all_trusted_devices))
+        .route("/auth/devices/:id", delete(handlers::revoke_trusted_device))
+        .route("/auth/device-authorizations", get(handlers::get_device_authorizations))
+        .route("/oauth/device", get(handlers::get_device_authorization).post(handlers::decide_device_authorization))
+
+        // Invitation routes
+        .route("/invitations", get(handlers::get_invitations).post(handlers::create_invitation))
+        .route("/invitations/:id", delete(handlers::revoke_invitation))
+
         // Category routes
-        // Commented out until implemented
-        // .route("/categories", get(handlers::get_categories))
-        // .route("/categories", post(handlers::create_category))
-        // .route("/categories/:id", put(handlers::update_category))
-        // .route("/categories/:id", delete(handlers::delete_category))
-        
+        .route("/categories", get(handlers::get_categories).post(handlers::create_category))
+        .route(
+            "/categories/by-path",
+            get(handlers::get_category_by_path).post(handlers::create_category_path),
+        )
+        .route("/categories/by-path/credentials", get(handlers::get_credentials_by_path))
+        .route("/categories/reorder", put(handlers::reorder_categories))
+        .route(
+            "/categories/:id",
+            get(handlers::get_category)
+                .put(handlers::update_category)
+                .delete(handlers::delete_category),
+        )
+        .route("/categories/:id/credentials", get(handlers::get_credentials_by_category))
+        .route("/categories/:id/revisions", get(handlers::get_category_revisions))
+        .route("/categories/:id/revisions/diff", get(handlers::diff_category_revisions))
+        .route("/categories/:id/revisions/:revision_id/rollback", post(handlers::rollback_category))
+        .route("/trash", get(handlers::get_trash))
+        .route("/trash/:id/restore", post(handlers::restore_trash_item))
+
         // Credential routes
-        // Commented out until implemented
-        // .route("/credentials", get(handlers::get_credentials))
-        // .route("/credentials", post(handlers::create_credential))
-        // .route("/credentials/:id", get(handlers::get_credential))
-        // .route("/credentials/:id", put(handlers::update_credential))
-        // .route("/credentials/:id", delete(handlers::delete_credential))
-        // .route("/categories/:id/credentials", get(handlers::get_credentials_by_category))
-        
+        .route("/credentials", get(handlers::get_credentials).post(handlers::create_credential))
+        .route(
+            "/credentials/:id",
+            get(handlers::get_credential_with_password)
+                .put(handlers::update_credential)
+                .delete(handlers::delete_credential),
+        )
+        .route("/credentials/:id/fields/:field_id", get(handlers::reveal_credential_field))
+        .route("/credentials/:id/password-history", get(handlers::get_password_history))
+        .route("/credentials/:id/password-history/:entry_id", get(handlers::reveal_password_history))
+        .route(
+            "/credentials/:id/password-history/:entry_id/restore",
+            post(handlers::restore_password_history),
+        )
+        .route("/credentials/:id/revisions", get(handlers::get_credential_revisions))
+        .route("/credentials/:id/revisions/diff", get(handlers::diff_credential_revisions))
+        .route(
+            "/credentials/:id/revisions/:revision_id/rollback",
+            post(handlers::rollback_credential),
+        )
+
         // Apply auth middleware to all routes
         .route_layer(middleware::from_fn_with_state(
             db_pool.clone(),
             require_auth,
         ));
-    
-    // Combine routes and add state
+
+    // Admin routes (admin role required)
+    let admin_routes = Router::new()
+        .route("/admin/users", get(handlers::list_users))
+        .route("/admin/users/:id", get(handlers::get_user))
+        .route("/admin/users/:id/disable", post(handlers::disable_user))
+        .route("/admin/users/:id/enable", post(handlers::enable_user))
+        .route("/admin/users/:id/force-password-reset", post(handlers::force_password_reset))
+        .route(
+            "/admin/users/:id/require-totp",
+            post(handlers::require_totp).delete(handlers::clear_totp_requirement),
+        )
+        .route("/admin/users/:id/revoke-tokens", post(handlers::revoke_user_tokens))
+        .route("/admin/users/:id/role", put(handlers::set_user_role))
+        .route(
+            "/admin/users/:id/ip-allowlist",
+            get(handlers::get_user_ip_allowlist).post(handlers::add_user_ip_allowlist_entry),
+        )
+        .route("/admin/users/:id/ip-allowlist/:entry_id", delete(handlers::remove_user_ip_allowlist_entry))
+        .route("/admin/audit-log", get(handlers::get_audit_log))
+        .route_layer(middleware::from_fn_with_state(
+            db_pool.clone(),
+            require_admin,
+        ));
+
+    // Combine routes, add state and the CORS layer
     Router::new()
-        .nest("/api", public_routes.merge(protected_routes))
+        .nest("/api", public_routes.merge(protected_routes).merge(admin_routes))
+        .layer(cors_layer())
+        .fallback(|| async { "Dragon Fruit Password Manager API" })
         .with_state(db_pool)
-} 
\ No newline at end of file
+}
@@ -0,0 +1,134 @@
+use time::OffsetDateTime;
+
+// Card numbers are stored without the spaces and dashes people type
+pub fn normalize_number(number: &str) -> String {
+    number.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+// Luhn checksum over a string of digits
+pub fn luhn_valid(digits: &str) -> bool {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let d = u32::from(b - b'0');
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+// Card network from the number's issuer prefix
+pub fn brand(digits: &str) -> Option<&'static str> {
+    let prefix = |n: usize| digits.get(..n).and_then(|p| p.parse::<u32>().ok());
+
+    if digits.starts_with('4') {
+        Some("visa")
+    } else if matches!(prefix(2), Some(51..=55)) || matches!(prefix(4), Some(2221..=2720)) {
+        Some("mastercard")
+    } else if matches!(prefix(2), Some(34 | 37)) {
+        Some("amex")
+    } else if digits.starts_with("6011") || digits.starts_with("65") || matches!(prefix(3), Some(644..=649)) {
+        Some("discover")
+    } else if matches!(prefix(4), Some(3528..=3589)) {
+        Some("jcb")
+    } else if matches!(prefix(2), Some(36 | 38 | 39)) || matches!(prefix(3), Some(300..=305)) {
+        Some("diners")
+    } else if digits.starts_with("62") {
+        Some("unionpay")
+    } else {
+        None
+    }
+}
+
+// Two-digit years are in this century
+pub fn normalize_year(year: u16) -> u16 {
+    if year < 100 { 2000 + year } else { year }
+}
+
+// A card is good through the last day of its expiry month
+pub fn is_expired(month: u8, year: u16, now: OffsetDateTime) -> bool {
+    (year, month) < (now.year() as u16, u8::from(now.month()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    fn at(year: i32, month: Month, day: u8) -> OffsetDateTime {
+        Date::from_calendar_date(year, month, day).unwrap().midnight().assume_utc()
+    }
+
+    #[test]
+    fn luhn_accepts_test_card_numbers() {
+        for number in ["4111111111111111", "5555555555554444", "378282246310005", "6011111111111117", "3530111333300000"] {
+            assert!(luhn_valid(number), "{}", number);
+        }
+    }
+
+    #[test]
+    fn luhn_rejects_bad_numbers() {
+        for number in ["4111111111111112", "", "4111 1111 1111 1111", "41111111abcd1111"] {
+            assert!(!luhn_valid(number), "{}", number);
+        }
+    }
+
+    #[test]
+    fn numbers_are_normalized_before_checking() {
+        assert_eq!(normalize_number("4111 1111-1111 1111"), "4111111111111111");
+    }
+
+    #[test]
+    fn brand_follows_issuer_prefix() {
+        let cases = [
+            ("4111111111111111", Some("visa")),
+            ("5555555555554444", Some("mastercard")),
+            ("2221000000000009", Some("mastercard")),
+            ("2720990000000000", Some("mastercard")),
+            ("2721000000000000", None),
+            ("378282246310005", Some("amex")),
+            ("341111111111111", Some("amex")),
+            ("6011111111111117", Some("discover")),
+            ("6445000000000000", Some("discover")),
+            ("3530111333300000", Some("jcb")),
+            ("30569309025904", Some("diners")),
+            ("36227206271667", Some("diners")),
+            ("6200000000000005", Some("unionpay")),
+            ("9999999999999999", None),
+        ];
+
+        for (number, expected) in cases {
+            assert_eq!(brand(number), expected, "{}", number);
+        }
+    }
+
+    #[test]
+    fn two_digit_years_are_this_century() {
+        assert_eq!(normalize_year(27), 2027);
+        assert_eq!(normalize_year(2031), 2031);
+    }
+
+    #[test]
+    fn card_is_good_through_its_expiry_month() {
+        // Expires 03/2025
+        assert!(!is_expired(3, 2025, at(2025, Month::March, 1)));
+        assert!(!is_expired(3, 2025, at(2025, Month::March, 31)));
+        assert!(is_expired(3, 2025, at(2025, Month::April, 1)));
+
+        // Year boundary: 12/2024 is still good on the last day of December
+        assert!(!is_expired(12, 2024, at(2024, Month::December, 31)));
+        assert!(is_expired(12, 2024, at(2025, Month::January, 1)));
+    }
+}
@@ -0,0 +1,323 @@
+use serde_json::Value;
+use time::{Date, Month};
+use uuid::Uuid;
+
+use crate::{
+    crypto::{decrypt_password, encrypt_password},
+    errors::AppError,
+    models::credential::{CredentialField, CustomFieldInput, CustomFieldResponse, FieldType},
+};
+
+// Limits on the custom fields of one credential
+pub const MAX_FIELDS: usize = 50;
+const MAX_LABEL_CHARS: usize = 100;
+const MAX_VALUE_CHARS: usize = 10_000;
+
+// A checked custom field, its value encoded for the value column
+#[derive(Debug)]
+pub struct FieldRow {
+    pub id: Uuid,
+    pub label: String,
+    pub field_type: FieldType,
+    pub value: String,
+}
+
+// Check the custom fields sent for a credential and encode them for storage.
+// `existing` holds the credential's current fields; one named by id keeps its
+// stored value when the input has none and the type is unchanged.
+pub fn parse_fields(
+    inputs: Vec<CustomFieldInput>,
+    existing: &[CredentialField],
+    user_id: Uuid,
+) -> Result<Vec<FieldRow>, AppError> {
+    if inputs.len() > MAX_FIELDS {
+        return Err(AppError::BadRequest(format!("A credential can have at most {} custom fields", MAX_FIELDS)));
+    }
+
+    let mut reasons = Vec::new();
+    let mut rows = Vec::with_capacity(inputs.len());
+
+    for (index, input) in inputs.into_iter().enumerate() {
+        let label = input.label.trim().to_string();
+        let name = if label.is_empty() { format!("Field {}", index + 1) } else { format!("Field \"{}\"", label) };
+
+        if label.is_empty() {
+            reasons.push(format!("{} needs a label", name));
+        } else if label.chars().count() > MAX_LABEL_CHARS {
+            reasons.push(format!("{} label must be at most {} characters", name, MAX_LABEL_CHARS));
+        }
+
+        let current = match input.id {
+            Some(id) if rows.iter().any(|row: &FieldRow| row.id == id) => {
+                reasons.push(format!("{} repeats an earlier field", name));
+                continue;
+            }
+            Some(id) => match existing.iter().find(|field| field.id == id) {
+                Some(field) => Some(field),
+                None => {
+                    reasons.push(format!("{} is not a field of this credential", name));
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let value = match (input.value, current) {
+            (Some(value), _) => match check_value(input.field_type, &value) {
+                Ok(value) if input.field_type == FieldType::Hidden => encrypt_password(&value, &user_id.to_string())
+                    .map_err(|e| AppError::Internal(format!("Failed to encrypt field: {}", e)))?,
+                Ok(value) => value,
+                Err(reason) => {
+                    reasons.push(format!("{} {}", name, reason));
+                    continue;
+                }
+            },
+            (None, Some(field)) if field.field_type == input.field_type.as_str() => field.value.clone(),
+            (None, Some(_)) => {
+                reasons.push(format!("{} needs a new value when its type changes", name));
+                continue;
+            }
+            (None, None) => {
+                reasons.push(format!("{} needs a value", name));
+                continue;
+            }
+        };
+
+        rows.push(FieldRow {
+            id: input.id.unwrap_or_else(Uuid::new_v4),
+            label,
+            field_type: input.field_type,
+            value,
+        });
+    }
+
+    if reasons.is_empty() {
+        Ok(rows)
+    } else {
+        Err(AppError::Validation("Invalid custom fields".to_string(), reasons))
+    }
+}
+
+// Check one value against its field type and return it as stored text, before encryption
+fn check_value(field_type: FieldType, value: &Value) -> Result<String, String> {
+    if field_type == FieldType::Boolean {
+        return value.as_bool().map(|b| b.to_string()).ok_or_else(|| "must be true or false".to_string());
+    }
+
+    let text = value.as_str().ok_or_else(|| "must be a string".to_string())?;
+    if text.chars().count() > MAX_VALUE_CHARS {
+        return Err(format!("must be at most {} characters", MAX_VALUE_CHARS));
+    }
+
+    match field_type {
+        FieldType::Url if !is_url(text.trim()) => Err("must be a URL such as https://example.com".to_string()),
+        FieldType::Url => Ok(text.trim().to_string()),
+        FieldType::Date => parse_date(text.trim())
+            .map(|date| date.to_string())
+            .ok_or_else(|| "must be a date in YYYY-MM-DD form".to_string()),
+        _ => Ok(text.to_string()),
+    }
+}
+
+// An absolute URL: a scheme, "://" and something after it, with no whitespace
+fn is_url(text: &str) -> bool {
+    let Some((scheme, rest)) = text.split_once("://") else {
+        return false;
+    };
+
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !rest.is_empty()
+        && !text.chars().any(char::is_whitespace)
+}
+
+// A calendar date written as YYYY-MM-DD
+fn parse_date(text: &str) -> Option<Date> {
+    let mut parts = text.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    if !text.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return None;
+    }
+
+    let month = Month::try_from(month.parse::<u8>().ok()?).ok()?;
+    Date::from_calendar_date(year.parse().ok()?, month, day.parse().ok()?).ok()
+}
+
+// The JSON value of a stored field. Hidden values are only decrypted when
+// `reveal_for` names the owner; otherwise they come back as null.
+pub fn field_value(field: &CredentialField, reveal_for: Option<Uuid>) -> Result<Option<Value>, AppError> {
+    let field_type = field.field_type.parse::<FieldType>().map_err(AppError::Internal)?;
+
+    let value = match field_type {
+        FieldType::Hidden => match reveal_for {
+            Some(user_id) => Value::String(
+                decrypt_password(&field.value, &user_id.to_string())
+                    .map_err(|e| AppError::Internal(format!("Failed to decrypt field: {}", e)))?,
+            ),
+            None => return Ok(None),
+        },
+        FieldType::Boolean => Value::Bool(field.value == "true"),
+        _ => Value::String(field.value.clone()),
+    };
+
+    Ok(Some(value))
+}
+
+pub fn field_response(field: &CredentialField, reveal_for: Option<Uuid>) -> Result<CustomFieldResponse, AppError> {
+    Ok(CustomFieldResponse {
+        id: field.id,
+        label: field.label.clone(),
+        field_type: field.field_type.clone(),
+        value: field_value(field, reveal_for)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use time::OffsetDateTime;
+
+    fn inputs(value: Value) -> Vec<CustomFieldInput> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn stored(id: Uuid, label: &str, field_type: &str, value: &str) -> CredentialField {
+        CredentialField {
+            id,
+            credential_id: Uuid::new_v4(),
+            position: 0,
+            label: label.to_string(),
+            field_type: field_type.to_string(),
+            value: value.to_string(),
+            created_at: OffsetDateTime::now_utc(),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn reasons(result: Result<Vec<FieldRow>, AppError>) -> Vec<String> {
+        match result {
+            Err(AppError::Validation(_, reasons)) => reasons,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn values_are_checked_and_encoded_by_type() {
+        let rows = parse_fields(
+            inputs(json!([
+                { "label": " Account ", "type": "text", "value": "  12345 " },
+                { "label": "Active", "type": "boolean", "value": true },
+                { "label": "Portal", "type": "url", "value": " https://example.com/login " },
+                { "label": "Renewal", "type": "date", "value": "2025-02-28" },
+            ])),
+            &[],
+            Uuid::new_v4(),
+        )
+        .unwrap();
+
+        let values: Vec<(&str, &str)> = rows.iter().map(|row| (row.label.as_str(), row.value.as_str())).collect();
+        assert_eq!(
+            values,
+            [("Account", "  12345 "), ("Active", "true"), ("Portal", "https://example.com/login"), ("Renewal", "2025-02-28")]
+        );
+    }
+
+    #[test]
+    fn unknown_id_is_rejected() {
+        let result = parse_fields(
+            inputs(json!([{ "id": Uuid::new_v4(), "label": "PIN", "type": "text" }])),
+            &[],
+            Uuid::new_v4(),
+        );
+
+        assert_eq!(reasons(result), ["Field \"PIN\" is not a field of this credential"]);
+    }
+
+    #[test]
+    fn existing_field_keeps_its_value_by_id() {
+        let id = Uuid::new_v4();
+        let existing = [stored(id, "Account", "text", "12345")];
+
+        let rows = parse_fields(
+            inputs(json!([{ "id": id, "label": "Account number", "type": "text" }])),
+            &existing,
+            Uuid::new_v4(),
+        )
+        .unwrap();
+
+        assert_eq!(rows[0].id, id);
+        assert_eq!(rows[0].label, "Account number");
+        assert_eq!(rows[0].value, "12345");
+    }
+
+    #[test]
+    fn type_change_needs_a_new_value() {
+        let id = Uuid::new_v4();
+        let existing = [stored(id, "Renewal", "text", "next spring")];
+
+        let result = parse_fields(
+            inputs(json!([{ "id": id, "label": "Renewal", "type": "date" }])),
+            &existing,
+            Uuid::new_v4(),
+        );
+
+        assert_eq!(reasons(result), ["Field \"Renewal\" needs a new value when its type changes"]);
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let result = parse_fields(
+            inputs(json!([
+                { "label": "", "type": "text", "value": "x" },
+                { "label": "Renewal", "type": "date", "value": "2025-02-30" },
+                { "label": "Portal", "type": "url", "value": "example.com" },
+                { "label": "Active", "type": "boolean", "value": "yes" },
+                { "label": "Notes", "type": "text" },
+            ])),
+            &[],
+            Uuid::new_v4(),
+        );
+
+        assert_eq!(
+            reasons(result),
+            [
+                "Field 1 needs a label",
+                "Field \"Renewal\" must be a date in YYYY-MM-DD form",
+                "Field \"Portal\" must be a URL such as https://example.com",
+                "Field \"Active\" must be true or false",
+                "Field \"Notes\" needs a value",
+            ]
+        );
+    }
+
+    #[test]
+    fn dates_must_be_real_calendar_dates() {
+        assert!(parse_date("2024-02-29").is_some());
+        for text in ["2023-02-29", "2024-13-01", "2024-1-01", "24-01-01", "2024-01-01-01", "+024-01-01", ""] {
+            assert!(parse_date(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn urls_need_a_scheme_and_no_whitespace() {
+        for text in ["https://example.com", "ssh://host:22", "git+ssh://example.com/repo"] {
+            assert!(is_url(text), "{}", text);
+        }
+        for text in ["example.com", "https://", "://example.com", "1http://example.com", "https://exa mple.com"] {
+            assert!(!is_url(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn long_values_are_rejected() {
+        let long = "x".repeat(MAX_VALUE_CHARS + 1);
+
+        assert_eq!(check_value(FieldType::Text, &json!(long)), Err(format!("must be at most {} characters", MAX_VALUE_CHARS)));
+        assert_eq!(check_value(FieldType::Text, &json!(42)), Err("must be a string".to_string()));
+    }
+}
@@ -0,0 +1,230 @@
+pub mod card;
+pub mod fields;
+pub mod ssh;
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    crypto::{decrypt_password, encrypt_password},
+    errors::AppError,
+    models::credential::{
+        ApiKeyData, CardData, IdentityData, ItemData, ItemType, SecureNoteData, SshKeyData,
+    },
+};
+
+// Upper bound on the encoded typed fields of one item
+const MAX_ITEM_DATA_BYTES: usize = 64 * 1024;
+
+fn from_value<T: DeserializeOwned>(item_type: ItemType, value: Value) -> Result<T, AppError> {
+    serde_json::from_value(value)
+        .map_err(|e| AppError::BadRequest(format!("Invalid {} data: {}", item_type.as_str(), e)))
+}
+
+fn is_blank(value: &Option<String>) -> bool {
+    value.as_deref().is_none_or(|v| v.trim().is_empty())
+}
+
+// Parse, normalise and check the typed fields sent for an item.
+// Logins have none; every other type requires them.
+pub fn parse_item_data(item_type: ItemType, data: Option<Value>) -> Result<Option<ItemData>, AppError> {
+    let value = match (item_type, data) {
+        (ItemType::Login, None) => return Ok(None),
+        (ItemType::Login, Some(_)) => {
+            return Err(AppError::BadRequest("Login items use username and password instead of data".to_string()));
+        }
+        (_, None) => return Err(AppError::BadRequest(format!("{} items require data", item_type.as_str()))),
+        (_, Some(value)) => value,
+    };
+
+    if value.to_string().len() > MAX_ITEM_DATA_BYTES {
+        return Err(AppError::BadRequest(format!("Item data must be at most {} bytes", MAX_ITEM_DATA_BYTES)));
+    }
+
+    let mut data = match item_type {
+        ItemType::Login => unreachable!(),
+        ItemType::SecureNote => ItemData::SecureNote(from_value(item_type, value)?),
+        ItemType::Card => ItemData::Card(from_value(item_type, value)?),
+        ItemType::Identity => ItemData::Identity(from_value(item_type, value)?),
+        ItemType::ApiKey => ItemData::ApiKey(from_value(item_type, value)?),
+        ItemType::SshKey => ItemData::SshKey(from_value(item_type, value)?),
+        ItemType::Database => ItemData::Database(from_value(item_type, value)?),
+    };
+
+    let reasons = check_item_data(&mut data);
+    if reasons.is_empty() {
+        Ok(Some(data))
+    } else {
+        Err(AppError::Validation(format!("Invalid {} data", item_type.as_str()), reasons))
+    }
+}
+
+// Check the fields of an item, normalising them in place, and collect every problem
+fn check_item_data(data: &mut ItemData) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    match data {
+        ItemData::SecureNote(SecureNoteData { text }) => {
+            if text.trim().is_empty() {
+                reasons.push("Note text cannot be empty".to_string());
+            }
+        }
+        ItemData::Card(card) => check_card(card, &mut reasons),
+        ItemData::Identity(identity) => check_identity(identity, &mut reasons),
+        ItemData::ApiKey(ApiKeyData { key, .. }) => {
+            if key.trim().is_empty() {
+                reasons.push("API key cannot be empty".to_string());
+            }
+        }
+        ItemData::SshKey(key) => check_ssh_key(key, &mut reasons),
+        ItemData::Database(database) => {
+            if database.engine.trim().is_empty() {
+                reasons.push("Database engine cannot be empty".to_string());
+            }
+            if database.host.trim().is_empty() {
+                reasons.push("Database host cannot be empty".to_string());
+            }
+            if database.port == Some(0) {
+                reasons.push("Database port must be between 1 and 65535".to_string());
+            }
+            database.engine = database.engine.trim().to_lowercase();
+        }
+    }
+
+    reasons
+}
+
+fn check_card(card: &mut CardData, reasons: &mut Vec<String>) {
+    card.number = card::normalize_number(&card.number);
+    card.expiry_year = card::normalize_year(card.expiry_year);
+
+    if card.cardholder.trim().is_empty() {
+        reasons.push("Cardholder name cannot be empty".to_string());
+    }
+
+    if !(12..=19).contains(&card.number.len()) || !card.number.chars().all(|c| c.is_ascii_digit()) {
+        reasons.push("Card number must be 12 to 19 digits".to_string());
+    } else if !card::luhn_valid(&card.number) {
+        reasons.push("Card number fails the Luhn check".to_string());
+    }
+
+    if !(1..=12).contains(&card.expiry_month) {
+        reasons.push("Expiry month must be between 1 and 12".to_string());
+    } else if card::is_expired(card.expiry_month, card.expiry_year, OffsetDateTime::now_utc()) {
+        reasons.push("Card has expired".to_string());
+    }
+
+    if let Some(cvv) = &card.cvv {
+        if !(3..=4).contains(&cvv.len()) || !cvv.chars().all(|c| c.is_ascii_digit()) {
+            reasons.push("CVV must be 3 or 4 digits".to_string());
+        }
+    }
+
+    if let Some(pin) = &card.pin {
+        if !(4..=12).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+            reasons.push("PIN must be 4 to 12 digits".to_string());
+        }
+    }
+}
+
+fn check_identity(identity: &IdentityData, reasons: &mut Vec<String>) {
+    let fields = [
+        &identity.title, &identity.first_name, &identity.last_name, &identity.email, &identity.phone,
+        &identity.address, &identity.city, &identity.postal_code, &identity.country,
+        &identity.date_of_birth, &identity.document_number,
+    ];
+    if fields.iter().all(|field| is_blank(field)) {
+        reasons.push("Identity must have at least one field".to_string());
+    }
+
+    if let Some(email) = identity.email.as_deref().filter(|email| !email.trim().is_empty()) {
+        let valid = matches!(email.trim().split_once('@'), Some((local, domain)) if !local.is_empty() && domain.contains('.'));
+        if !valid {
+            reasons.push("Identity email is not a valid address".to_string());
+        }
+    }
+}
+
+fn check_ssh_key(key: &mut SshKeyData, reasons: &mut Vec<String>) {
+    if is_blank(&key.private_key) && is_blank(&key.public_key) {
+        reasons.push("SSH key needs a private key, a public key or both".to_string());
+        return;
+    }
+
+    let from_private = match key.private_key.as_deref().filter(|pem| !pem.trim().is_empty()) {
+        Some(pem) if !ssh::is_private_key(pem) => {
+            reasons.push("Private key must be in PEM format".to_string());
+            None
+        }
+        Some(pem) => ssh::public_key_from_private(pem).unwrap_or_else(|e| {
+            reasons.push(e);
+            None
+        }),
+        None => None,
+    };
+
+    let given = match key.public_key.as_deref().filter(|line| !line.trim().is_empty()) {
+        Some(line) => match ssh::parse_public_key(line) {
+            Ok(public_key) => Some(public_key),
+            Err(e) => {
+                reasons.push(e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    match (given, from_private) {
+        (Some(given), Some(derived)) if given.blob != derived.blob => {
+            reasons.push("Public key does not match the private key".to_string());
+        }
+        (None, Some(derived)) if reasons.is_empty() => key.public_key = Some(derived.to_line()),
+        _ => {}
+    }
+}
+
+// Non-secret details shown in credential lists without decrypting anything
+pub fn item_metadata(data: Option<&ItemData>) -> Value {
+    match data {
+        Some(ItemData::Card(card)) => json!({
+            "brand": card::brand(&card.number),
+            "last4": &card.number[card.number.len().saturating_sub(4)..],
+            "expiry": format!("{:02}/{}", card.expiry_month, card.expiry_year),
+        }),
+        Some(ItemData::SshKey(key)) => match key.public_key.as_deref().map(ssh::parse_public_key) {
+            Some(Ok(public_key)) => json!({
+                "key_type": public_key.key_type,
+                "fingerprint": public_key.fingerprint(),
+                "comment": public_key.comment,
+            }),
+            _ => json!({}),
+        },
+        Some(ItemData::Database(database)) => json!({
+            "engine": database.engine,
+            "host": database.host,
+            "port": database.port,
+        }),
+        Some(ItemData::ApiKey(api_key)) => json!({ "endpoint": api_key.endpoint }),
+        _ => json!({}),
+    }
+}
+
+// Encrypt typed fields for the payload column
+pub fn seal_item_data(data: &ItemData, user_id: Uuid) -> Result<String, AppError> {
+    let plaintext = serde_json::to_string(data)
+        .map_err(|e| AppError::Internal(format!("Failed to encode item data: {}", e)))?;
+
+    encrypt_password(&plaintext, &user_id.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to encrypt item data: {}", e)))
+}
+
+// Decrypt the payload column back to JSON
+pub fn open_item_data(payload: &str, user_id: Uuid) -> Result<Value, AppError> {
+    let plaintext = decrypt_password(payload, &user_id.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to decrypt item data: {}", e)))?;
+
+    serde_json::from_str(&plaintext)
+        .map_err(|e| AppError::Internal(format!("Failed to decode item data: {}", e)))
+}
@@ -0,0 +1,174 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use dragon::routes::create_router;
+use sqlx::postgres::PgPoolOptions;
+use tower::Service;
+
+const ID: &str = "00000000-0000-0000-0000-000000000001";
+
+// The pool never connects: every request below is turned away before a query runs
+fn app() -> Router {
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost/dragonfruit_test")
+        .expect("valid database URL");
+    create_router(pool)
+}
+
+async fn status(app: &mut Router, method: Method, path: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(path)
+        .body(Body::empty())
+        .unwrap();
+    app.call(request).await.unwrap().status()
+}
+
+// A mounted protected route answers 401; an unmounted one would fall through to 200
+async fn assert_protected(routes: &[(Method, String)]) {
+    let mut app = app();
+    for (method, path) in routes {
+        assert_eq!(
+            status(&mut app, method.clone(), path).await,
+            StatusCode::UNAUTHORIZED,
+            "{} {}",
+            method,
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn category_routes_are_mounted() {
+    assert_protected(&[
+        (Method::GET, "/api/categories".to_string()),
+        (Method::POST, "/api/categories".to_string()),
+        (Method::GET, format!("/api/categories/{}", ID)),
+        (Method::PUT, format!("/api/categories/{}", ID)),
+        (Method::DELETE, format!("/api/categories/{}", ID)),
+        (Method::GET, format!("/api/categories/{}/credentials", ID)),
+        (Method::GET, "/api/categories/by-path?path=Infra/AWS".to_string()),
+        (Method::POST, "/api/categories/by-path".to_string()),
+        (Method::GET, "/api/categories/by-path/credentials?path=Infra&recursive=true".to_string()),
+        (Method::PUT, "/api/categories/reorder".to_string()),
+        (Method::GET, format!("/api/categories/{}/revisions", ID)),
+        (Method::GET, format!("/api/categories/{}/revisions/diff?from={}", ID, ID)),
+        (Method::POST, format!("/api/categories/{}/revisions/{}/rollback", ID, ID)),
+        (Method::GET, "/api/trash".to_string()),
+        (Method::POST, format!("/api/trash/{}/restore", ID)),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn credential_routes_are_mounted() {
+    assert_protected(&[
+        (Method::GET, "/api/credentials".to_string()),
+        (Method::POST, "/api/credentials".to_string()),
+        (Method::GET, format!("/api/credentials/{}", ID)),
+        (Method::PUT, format!("/api/credentials/{}", ID)),
+        (Method::DELETE, format!("/api/credentials/{}", ID)),
+        (Method::GET, format!("/api/credentials/{}/fields/{}", ID, ID)),
+        (Method::GET, format!("/api/credentials/{}/password-history", ID)),
+        (Method::GET, format!("/api/credentials/{}/password-history/{}", ID, ID)),
+        (Method::POST, format!("/api/credentials/{}/password-history/{}/restore", ID, ID)),
+        (Method::GET, format!("/api/credentials/{}/revisions", ID)),
+        (Method::GET, format!("/api/credentials/{}/revisions/diff?from={}", ID, ID)),
+        (Method::POST, format!("/api/credentials/{}/revisions/{}/rollback", ID, ID)),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn account_routes_are_mounted() {
+    assert_protected(&[
+        (Method::GET, "/api/auth/profile".to_string()),
+        (Method::PUT, "/api/auth/profile".to_string()),
+        (Method::GET, "/api/auth/csrf".to_string()),
+        (Method::POST, "/api/auth/password".to_string()),
+        (Method::POST, "/api/auth/email".to_string()),
+        (Method::POST, "/api/auth/account/delete".to_string()),
+        (Method::POST, "/api/auth/totp/generate".to_string()),
+        (Method::POST, "/api/auth/totp/enable".to_string()),
+        (Method::POST, "/api/auth/email-otp/enable".to_string()),
+        (Method::POST, "/api/auth/email-otp/disable".to_string()),
+        (Method::GET, "/api/auth/login-history".to_string()),
+        (Method::GET, "/api/auth/ip-allowlist".to_string()),
+        (Method::POST, "/api/auth/ip-allowlist".to_string()),
+        (Method::DELETE, format!("/api/auth/ip-allowlist/{}", ID)),
+        (Method::GET, "/api/auth/devices".to_string()),
+        (Method::DELETE, "/api/auth/devices".to_string()),
+        (Method::DELETE, format!("/api/auth/devices/{}", ID)),
+        (Method::GET, "/api/auth/device-authorizations".to_string()),
+        (Method::GET, "/api/oauth/device?user_code=BCDF-GHJK".to_string()),
+        (Method::POST, "/api/oauth/device".to_string()),
+        (Method::GET, "/api/invitations".to_string()),
+        (Method::POST, "/api/invitations".to_string()),
+        (Method::DELETE, format!("/api/invitations/{}", ID)),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn admin_routes_are_mounted() {
+    assert_protected(&[
+        (Method::GET, "/api/admin/users".to_string()),
+        (Method::GET, format!("/api/admin/users/{}", ID)),
+        (Method::POST, format!("/api/admin/users/{}/disable", ID)),
+        (Method::POST, format!("/api/admin/users/{}/enable", ID)),
+        (Method::POST, format!("/api/admin/users/{}/force-password-reset", ID)),
+        (Method::POST, format!("/api/admin/users/{}/require-totp", ID)),
+        (Method::DELETE, format!("/api/admin/users/{}/require-totp", ID)),
+        (Method::POST, format!("/api/admin/users/{}/revoke-tokens", ID)),
+        (Method::PUT, format!("/api/admin/users/{}/role", ID)),
+        (Method::GET, format!("/api/admin/users/{}/ip-allowlist", ID)),
+        (Method::POST, format!("/api/admin/users/{}/ip-allowlist", ID)),
+        (Method::DELETE, format!("/api/admin/users/{}/ip-allowlist/{}", ID, ID)),
+        (Method::GET, "/api/admin/audit-log".to_string()),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn public_routes_do_not_require_auth() {
+    let mut app = app();
+
+    // Without a JSON body the request is rejected before reaching the database
+    for path in [
+        "/api/auth/register",
+        "/api/auth/login",
+        "/api/auth/verify-email",
+        "/api/auth/verify-email/resend",
+        "/api/auth/password-reset/request",
+        "/api/auth/password-reset/confirm",
+        "/api/auth/email/confirm",
+        "/api/auth/email/cancel",
+    ] {
+        assert_eq!(
+            status(&mut app, Method::POST, path).await,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "POST {}",
+            path
+        );
+    }
+
+    assert_eq!(status(&mut app, Method::POST, "/api/auth/logout").await, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn unsupported_methods_are_rejected() {
+    let mut app = app();
+
+    // Protected routes authenticate first, so this is only visible on public ones
+    assert_eq!(status(&mut app, Method::GET, "/api/auth/login").await, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(status(&mut app, Method::GET, "/api/oauth/token").await, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn unknown_paths_fall_back() {
+    let mut app = app();
+
+    assert_eq!(status(&mut app, Method::GET, "/").await, StatusCode::OK);
+}
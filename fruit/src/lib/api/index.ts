@@ -1,6 +1,7 @@
 import { auth } from '$lib/stores/auth';
 import { get } from 'svelte/store';
 import { browser } from '$app/environment';
+import type { CategoryDeleteStrategy } from '$lib/types';
 
 const API_URL = import.meta.env.VITE_API_URL;
 
@@ -37,7 +38,14 @@ async function api(endpoint: string, options: ApiOptions = {}) {
     headers['Authorization'] = `Bearer ${token}`;
   }
 
-  const config: RequestInit = { method, headers };
+  // Cookie sessions need the CSRF token on anything that changes state
+  const { csrfToken } = get(auth);
+  if (csrfToken && method !== 'GET') {
+    headers['X-CSRF-Token'] = csrfToken;
+  }
+
+  // Send the session cookie to the API origin
+  const config: RequestInit = { method, headers, credentials: 'include' };
 
   if (body) {
     config.body = contentType === 'application/json' ? JSON.stringify(body) : body;
@@ -69,6 +77,8 @@ export default {
     login: (credentials: { email: string, password: string, totp_code?: string }) => 
       api('/auth/login', { method: 'POST', body: credentials }),
     
+    logout: () => api('/auth/logout', { method: 'POST' }),
+    
     getProfile: () => api('/auth/profile'),
     
     updateProfile: (userData: { email?: string, password?: string }) => 
@@ -90,8 +100,15 @@ export default {
     update: (id: string, categoryData: { name: string, parent_id?: string | null }) => 
       api(`/categories/${id}`, { method: 'PUT', body: categoryData }),
     
-    delete: (id: string) => 
-      api(`/categories/${id}`, { method: 'DELETE' })
+    delete: (id: string, strategy: CategoryDeleteStrategy = 'reject') => 
+      api(`/categories/${id}?strategy=${strategy}`, { method: 'DELETE' })
+  },
+
+  // Trash endpoints
+  trash: {
+    getAll: () => api('/trash'),
+
+    restore: (id: string) => api(`/trash/${id}/restore`, { method: 'POST' })
   },
   
   // Credentials endpoints
@@ -9,7 +9,8 @@ interface User {
 
 interface AuthState {
   user: User | null;
-  token: string | null;
+  token: string | null;     // Bearer mode only
+  csrfToken: string | null; // Cookie mode only; the session itself is an HttpOnly cookie
   loading: boolean;
   error: string | null;
 }
@@ -17,10 +18,16 @@ interface AuthState {
 const defaultState: AuthState = {
   user: null,
   token: null,
+  csrfToken: null,
   loading: false,
   error: null
 };
 
+// Signed in with either a bearer token or a session cookie
+export function isSignedIn(state: AuthState): boolean {
+  return Boolean(state.token || state.csrfToken);
+}
+
 function createAuthStore() {
   // Initialize from localStorage if in browser
   const initialState = browser 
@@ -40,6 +47,15 @@ function createAuthStore() {
       });
     },
     
+    // Login responses carry either a token or a CSRF token, depending on the server's session mode
+    setSession: (token: string | null, csrfToken: string | null) => {
+      update(state => {
+        const newState = { ...state, token, csrfToken, error: null };
+        if (browser) localStorage.setItem('auth', JSON.stringify(newState));
+        return newState;
+      });
+    },
+    
     setUser: (user: User) => {
       update(state => {
         const newState = { ...state, user, error: null };
@@ -1,6 +1,6 @@
 import { writable } from 'svelte/store';
 import api from '$lib/api';
-import type { Category } from '$lib/types';
+import type { Category, CategoryDeleteStrategy } from '$lib/types';
 
 interface CategoryState {
   categories: Category[];
@@ -68,16 +68,16 @@ function createCategoryStore() {
       }
     },
 
-    deleteCategory: async (id: string) => {
+    deleteCategory: async (id: string, strategy: CategoryDeleteStrategy = 'reject') => {
       update(state => ({ ...state, loading: true, error: null }));
       
       try {
-        await api.categories.delete(id);
+        const result = await api.categories.delete(id, strategy);
         
         // Refetch to get the updated nested structure
         await categories.fetchCategories();
         
-        return true;
+        return result;
       } catch (err) {
         const message = err instanceof Error ? err.message : 'Failed to delete category';
         update(state => ({ ...state, loading: false, error: message }));
@@ -9,20 +9,94 @@ export interface User {
 export interface Category {
   id: string;
   name: string;
+  description: string | null;
   parent_id: string | null;
+  position: number;
+  color: string | null;
+  icon: string | null;
+  collapsed: boolean;
+  credential_count: number;
+  subtree_credential_count: number;
   created_at: string;
   updated_at: string;
   children: Category[];
 }
 
+export type CategoryDeleteStrategy = 'reject' | 'move_to_parent' | 'move_to_unfiled' | 'trash';
+
+export interface CategoryDeletion {
+  strategy: CategoryDeleteStrategy;
+  deleted_categories: string[];
+  moved_to: string | null;
+  moved_categories: string[];
+  moved_credentials: string[];
+  trashed_credentials: string[];
+}
+
+export interface TrashItem {
+  id: string;
+  item_type: 'category' | 'credential';
+  item_id: string;
+  name: string | null;
+  deleted_at: string;
+}
+
+export interface TrashRestore {
+  restored_categories: string[];
+  restored_credentials: string[];
+}
+
+export type ItemType = 'login' | 'secure_note' | 'card' | 'identity' | 'api_key' | 'ssh_key' | 'database';
+
+export type CustomFieldType = 'text' | 'hidden' | 'boolean' | 'url' | 'date';
+
+export interface CustomField {
+  id: string;
+  label: string;
+  type: CustomFieldType;
+  value: string | boolean | null; // Null for hidden fields unless revealed
+}
+
 export interface Credential {
   id: string;
+  item_type: ItemType;
+  metadata: Record<string, unknown>; // Non-secret summary, e.g. a card's brand and last four digits
   name: string;
   category_id: string | null;
   website: string | null;
   username: string | null;
   password?: string; // Only included in single credential response
+  data?: Record<string, unknown> | null; // Typed fields of non-login items, single credential response only
+  fields: CustomField[];
   notes: string | null;
   created_at: string;
   updated_at: string;
-} 
\ No newline at end of file
+} 
+
+export interface PasswordHistoryEntry {
+  id: string;
+  password: string | null; // Null in lists; set when revealed
+  replaced_at: string;
+}
+
+export interface Revision {
+  id: string;
+  version: number;
+  changed_by: string;
+  changed_fields: string[]; // Empty for version 1, the state before the first recorded change
+  rollback_of: string | null;
+  created_at: string;
+}
+
+export interface FieldChange {
+  field: string;
+  from: unknown;
+  to: unknown;
+  secret: boolean; // Encrypted values changed; from and to leave them out
+}
+
+export interface RevisionDiff {
+  from: string;
+  to: string | null; // Null when compared with the current state
+  changes: FieldChange[];
+}